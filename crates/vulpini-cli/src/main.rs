@@ -4,8 +4,8 @@ use std::sync::Arc;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
-use vulpini_core::config::ConfigStore;
-use vulpini_core::node::{Node, NodeSource, parse_link};
+use vulpini_core::config::{ConfigStore, LinkImport};
+use vulpini_core::node::NodeSource;
 
 #[derive(Parser)]
 #[command(
@@ -26,9 +26,16 @@ struct Cli {
 enum Command {
     /// Run the proxy core in the foreground.
     Run {
-        /// Listen address for the mixed SOCKS5/HTTP inbound.
+        /// Listen address for the mixed SOCKS5/SOCKS4/HTTP inbound.
         #[arg(long)]
         listen: Option<String>,
+        /// Also write the end-of-run summary (JSON) to this file.
+        #[arg(long)]
+        summary_file: Option<PathBuf>,
+        /// Check that node servers accept TCP connections before listening
+        /// and report how many do (failures only warn; the core still starts).
+        #[arg(long)]
+        preflight: bool,
     },
     /// Import nodes from share links (one per argument).
     Import {
@@ -115,7 +122,11 @@ async fn main() -> Result<()> {
 
     let cli = Cli::parse();
     match cli.command {
-        Command::Run { listen } => {
+        Command::Run {
+            listen,
+            summary_file,
+            preflight,
+        } => {
             let mut store = ConfigStore::load(&cli.config)?;
             let addr = match listen {
                 Some(l) => l.parse()?,
@@ -145,6 +156,30 @@ async fn main() -> Result<()> {
                 },
                 None => eprintln!("warning: no active node; 'proxy' outbound will fail"),
             }
+            if preflight {
+                let config = store.config();
+                let nodes = &config.nodes;
+                let summary = vulpini_core::delay::preflight(
+                    nodes,
+                    config.proxy.delay_timeout(),
+                    config.engine.outbound_bind_address,
+                    8,
+                )
+                .await;
+                println!(
+                    "preflight: {}/{} node servers reachable",
+                    summary.reachable.len(),
+                    nodes.len()
+                );
+                for (name, error) in &summary.unreachable {
+                    let marker = if active.is_some_and(|n| &n.name == name) {
+                        " (active)"
+                    } else {
+                        ""
+                    };
+                    eprintln!("warning: preflight: '{name}'{marker} unreachable ({error})");
+                }
+            }
 
             let config = store.config();
             let router = match vulpini_core::Router::from_config(config.mode, &config.rules) {
@@ -165,9 +200,13 @@ async fn main() -> Result<()> {
             }
             let router = router.with_geo(geo);
 
-            let engine =
-                vulpini_core::EngineHandle::start_with_fallback(addr, Arc::new(registry), router)
-                    .await?;
+            let engine = vulpini_core::EngineHandle::start_with_fallback(
+                addr,
+                Arc::new(registry),
+                router,
+                store.config().engine.clone(),
+            )
+            .await?;
             if engine.local_addr() != addr {
                 println!(
                     "warning: port {} unavailable, using {} instead",
@@ -178,12 +217,17 @@ async fn main() -> Result<()> {
                 store.save().ok();
             }
             println!(
-                "vulpini listening on {} (mixed socks5/http)",
+                "vulpini listening on {} (mixed socks5/socks4/http)",
                 engine.local_addr()
             );
             tokio::signal::ctrl_c().await?;
             println!("shutting down...");
-            engine.shutdown().await;
+            let summary = engine.shutdown().await;
+            let json = serde_json::to_string_pretty(&summary)?;
+            println!("{json}");
+            if let Some(path) = summary_file {
+                std::fs::write(&path, json)?;
+            }
         }
         Command::Import { links } => cmd_import(&cli.config, links)?,
         Command::List => cmd_list(&cli.config)?,
@@ -337,19 +381,18 @@ fn cmd_import(path: &std::path::Path, links: Vec<String>) -> Result<()> {
     let mut added = 0usize;
     let mut failed = 0usize;
 
-    for link in &links {
-        match parse_link(link) {
-            Ok((name, config)) => {
-                let node = Node::new(name, NodeSource::Manual, config);
-                if store
+    let outcomes = store
+        .config_mut()
+        .import_links(links.iter().map(String::as_str));
+    for outcome in outcomes {
+        match outcome {
+            LinkImport::Added(id) => {
+                let node = store
                     .config()
                     .nodes
                     .iter()
-                    .any(|n| n.stable_key == node.stable_key)
-                {
-                    println!("skip (duplicate): {}", node.name);
-                    continue;
-                }
+                    .find(|n| n.id == id)
+                    .expect("just added");
                 println!(
                     "added: {} [{}] {}:{}",
                     node.name,
@@ -357,10 +400,10 @@ fn cmd_import(path: &std::path::Path, links: Vec<String>) -> Result<()> {
                     node.config.server(),
                     node.config.port()
                 );
-                store.config_mut().nodes.push(node);
                 added += 1;
             }
-            Err(e) => {
+            LinkImport::Duplicate(name) => println!("skip (duplicate): {name}"),
+            LinkImport::Invalid(e) => {
                 println!("failed: {e}");
                 failed += 1;
             }
@@ -449,12 +492,13 @@ async fn cmd_delay(path: &std::path::Path, all: bool) -> Result<()> {
         "testing {} node(s) via {} (timeout {}s) ...",
         targets.len(),
         store.config().proxy.probe_url,
-        store.config().proxy.delay_timeout_secs
+        store.config().proxy.delay_timeout().as_secs()
     );
     let mut results = vulpini_core::delay::test_all(
         targets,
         store.config().proxy.probe_url.clone(),
-        std::time::Duration::from_secs(store.config().proxy.delay_timeout_secs),
+        store.config().proxy.delay_timeout(),
+        store.config().engine.outbound_bind_address,
         8,
     );
     while let Some(result) = results.next().await {
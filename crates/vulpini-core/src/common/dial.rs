@@ -0,0 +1,77 @@
+//! The TCP dial shared by every outbound, so a configured source address
+//! applies to direct targets and proxy servers alike.
+
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+
+use tokio::net::{TcpSocket, TcpStream, ToSocketAddrs, lookup_host};
+use tracing::warn;
+
+/// Connect to `addr`, from local address `bind` when one is set. A
+/// resolved address of the other family (v6 target, v4 bind) is dialed
+/// unbound with a warning rather than failed outright.
+pub async fn tcp_connect(addr: impl ToSocketAddrs, bind: Option<IpAddr>) -> io::Result<TcpStream> {
+    let Some(bind) = bind else {
+        return TcpStream::connect(addr).await;
+    };
+    let mut last_err = None;
+    for target in lookup_host(addr).await? {
+        match connect_from(target, bind).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any address",
+        )
+    }))
+}
+
+async fn connect_from(target: SocketAddr, bind: IpAddr) -> io::Result<TcpStream> {
+    if target.is_ipv4() != bind.is_ipv4() {
+        warn!(
+            %target,
+            %bind,
+            "outbound bind address family mismatch, connecting unbound"
+        );
+        return TcpStream::connect(target).await;
+    }
+    let socket = if target.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.bind(SocketAddr::new(bind, 0))?;
+    socket.connect(target).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn connects_from_the_bind_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bind: IpAddr = "127.0.0.2".parse().unwrap();
+
+        let stream = tcp_connect(addr, Some(bind)).await.unwrap();
+        assert_eq!(stream.local_addr().unwrap().ip(), bind);
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer.ip(), bind);
+    }
+
+    #[tokio::test]
+    async fn family_mismatch_falls_back_to_unbound() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let stream = tcp_connect(addr, Some("::1".parse().unwrap()))
+            .await
+            .unwrap();
+        assert!(stream.local_addr().unwrap().is_ipv4());
+    }
+}
@@ -23,6 +23,12 @@ pub enum CoreError {
     #[error("connection timed out")]
     Timeout,
 
+    #[error("proxy authentication failed: {0}")]
+    Auth(String),
+
+    #[error("service unavailable: {0}")]
+    Unavailable(String),
+
     #[error("http error: {0}")]
     Http(#[from] reqwest::Error),
 }
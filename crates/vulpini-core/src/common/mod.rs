@@ -1,7 +1,9 @@
 pub mod addr;
+pub mod dial;
 pub mod error;
 pub mod session;
 pub mod stream;
+pub mod throttle;
 
 pub use addr::{Address, parse_host_port};
 pub use error::CoreError;
@@ -1,3 +1,5 @@
+use std::net::IpAddr;
+
 use super::Address;
 
 /// One proxied connection as seen by the engine: a target plus metadata
@@ -8,6 +10,11 @@ pub struct Session {
     pub network: Network,
     /// Which inbound accepted this connection ("socks5" / "http").
     pub inbound_tag: &'static str,
+    /// Inbound user the client authenticated as (proxy auth on only).
+    pub user: Option<String>,
+    /// Local source address for the outbound socket
+    /// (`engine.outbound_bind_address`); None lets the OS choose.
+    pub bind: Option<IpAddr>,
 }
 
 impl Session {
@@ -16,6 +23,8 @@ impl Session {
             target,
             network: Network::Tcp,
             inbound_tag,
+            user: None,
+            bind: None,
         }
     }
 }
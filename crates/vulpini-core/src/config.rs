@@ -1,12 +1,15 @@
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tracing::warn;
 use uuid::Uuid;
 
+use crate::engine::EngineConfig;
 use crate::geo::GeoConfig;
-use crate::node::{Node, NodeId};
+use crate::node::{LinkError, Node, NodeId, NodeSource, parse_link};
 use crate::router::Mode;
 
 /// Persisted application configuration (JSON on disk). Runtime state lives
@@ -43,6 +46,10 @@ pub struct AppConfig {
     /// Proxy behavior knobs, all user-editable.
     #[serde(default)]
     pub proxy: ProxySettings,
+    /// Engine tuning knobs (advanced; edited in the file, not the UI).
+    /// Parsed leniently: a bad value here resets only this section.
+    #[serde(default, deserialize_with = "lenient_engine")]
+    pub engine: EngineConfig,
 }
 
 /// Proxy-related settings (the "代理" group in Settings).
@@ -63,6 +70,18 @@ pub struct ProxySettings {
     pub sysproxy_override: String,
 }
 
+impl ProxySettings {
+    /// Delay-test and preflight timeout; 0 falls back to the default
+    /// rather than failing every node at once.
+    pub fn delay_timeout(&self) -> Duration {
+        let secs = match self.delay_timeout_secs {
+            0 => default_delay_timeout_secs(),
+            secs => secs,
+        };
+        Duration::from_secs(secs)
+    }
+}
+
 impl Default for ProxySettings {
     fn default() -> Self {
         ProxySettings {
@@ -94,6 +113,19 @@ pub struct SysProxyBackup {
     pub server: Option<String>,
 }
 
+/// Hand edits land in `engine`, so a typo there must not take the nodes
+/// and subscriptions down with it.
+fn lenient_engine<'de, D>(deserializer: D) -> Result<EngineConfig, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(EngineConfig::deserialize(value).unwrap_or_else(|e| {
+        warn!(error = %e, "invalid engine config, using defaults");
+        EngineConfig::default()
+    }))
+}
+
 fn default_mode() -> Mode {
     Mode::Rule
 }
@@ -117,10 +149,145 @@ impl Default for AppConfig {
             system_proxy_enabled: false,
             sysproxy_backup: None,
             proxy: ProxySettings::default(),
+            engine: EngineConfig::default(),
         }
     }
 }
 
+/// Field names whose values never appear in a [`ConfigChange`]: proxy
+/// passwords, node passwords and uuids, and subscription urls (which
+/// usually embed an access token).
+const SECRET_FIELDS: [&str; 3] = ["password", "uuid", "url"];
+
+const REDACTED: &str = "***";
+
+/// `value` with every secret field inside it masked.
+fn redacted(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, v)| {
+                    let v = if SECRET_FIELDS.contains(&key.as_str()) {
+                        Value::from(REDACTED)
+                    } else {
+                        redacted(v)
+                    };
+                    (key.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redacted).collect()),
+        v => v.clone(),
+    }
+}
+
+/// One field that differs between two configs; see [`AppConfig::diff`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    /// Dotted JSON path; array elements by index ("nodes.0.name").
+    pub path: String,
+    /// None when the field did not exist before.
+    pub old: Option<Value>,
+    /// None when the field no longer exists.
+    pub new: Option<Value>,
+}
+
+impl AppConfig {
+    /// Field-level changes from `self` to `other`, compared on the
+    /// serialized form so every persisted field is covered. Secret
+    /// values are replaced with "***"; the change itself still shows.
+    pub fn diff(&self, other: &AppConfig) -> Vec<ConfigChange> {
+        let old = serde_json::to_value(self).expect("config serializes");
+        let new = serde_json::to_value(other).expect("config serializes");
+        let mut changes = Vec::new();
+        diff_values(String::new(), Some(&old), Some(&new), &mut changes);
+        changes
+    }
+}
+
+fn diff_values(
+    path: String,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    out: &mut Vec<ConfigChange>,
+) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match (old, new) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                diff_values(join(key), a.get(key), b.get(key), out);
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => {
+            for i in 0..a.len().max(b.len()) {
+                diff_values(join(&i.to_string()), a.get(i), b.get(i), out);
+            }
+        }
+        (a, b) if a == b => {}
+        (a, b) => {
+            let secret = path
+                .rsplit('.')
+                .next()
+                .is_some_and(|field| SECRET_FIELDS.contains(&field));
+            let shown = |v: Option<&Value>| match v {
+                Some(_) if secret => Some(Value::from(REDACTED)),
+                v => v.map(redacted),
+            };
+            out.push(ConfigChange {
+                old: shown(a),
+                new: shown(b),
+                path,
+            });
+        }
+    }
+}
+
+/// What [`AppConfig::import_links`] did with one share link.
+#[derive(Debug)]
+pub enum LinkImport {
+    /// Appended as a manual node.
+    Added(NodeId),
+    /// Same node (stable_key) as one already in the config or earlier in
+    /// the batch; carries the skipped link's name.
+    Duplicate(String),
+    Invalid(LinkError),
+}
+
+impl AppConfig {
+    /// Parse share links and append each new one as a manual node, in
+    /// input order. One outcome per link.
+    pub fn import_links<'a>(
+        &mut self,
+        links: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<LinkImport> {
+        links
+            .into_iter()
+            .map(|link| match parse_link(link) {
+                Err(e) => LinkImport::Invalid(e),
+                Ok((name, config)) => {
+                    let node = Node::new(name, NodeSource::Manual, config);
+                    if self.nodes.iter().any(|n| n.stable_key == node.stable_key) {
+                        LinkImport::Duplicate(node.name)
+                    } else {
+                        let id = node.id;
+                        self.nodes.push(node);
+                        LinkImport::Added(id)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Subscription {
     pub id: Uuid,
@@ -143,20 +310,18 @@ pub struct ConfigStore {
 }
 
 impl ConfigStore {
-    /// Load from `path`; a missing file yields defaults, a corrupt file is
-    /// backed up to `<path>.bad` and replaced with defaults.
+    /// Load from `path`; a missing file yields defaults. A corrupt file is
+    /// an `InvalidData` error and is left untouched — starting from
+    /// defaults would overwrite the user's nodes on the next save.
     pub fn load(path: impl Into<PathBuf>) -> std::io::Result<Self> {
         let path = path.into();
         let config = match std::fs::read_to_string(&path) {
-            Ok(text) => match serde_json::from_str::<AppConfig>(&text) {
-                Ok(config) => config,
-                Err(e) => {
-                    let backup = path.with_extension("bad");
-                    warn!(error = %e, backup = %backup.display(), "config corrupt, starting fresh");
-                    let _ = std::fs::copy(&path, &backup);
-                    AppConfig::default()
-                }
-            },
+            Ok(text) => serde_json::from_str::<AppConfig>(&text).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("config file {} is corrupt: {e}", path.display()),
+                )
+            })?,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => AppConfig::default(),
             Err(e) => return Err(e),
         };
@@ -187,7 +352,7 @@ impl ConfigStore {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::node::{NodeConfig, NodeSource, SsConfig, SsMethod};
+    use crate::node::{NodeConfig, SsConfig, SsMethod};
 
     fn sample_node() -> Node {
         Node::new(
@@ -223,14 +388,114 @@ mod tests {
     }
 
     #[test]
-    fn corrupt_file_is_backed_up() {
+    fn corrupt_file_is_refused() {
         let dir = tempfile::tempdir().unwrap();
         let path = dir.path().join("config.json");
         std::fs::write(&path, "{ not json").unwrap();
 
+        let err = ConfigStore::load(&path)
+            .err()
+            .expect("corrupt file must not load");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{ not json");
+    }
+
+    #[test]
+    fn zero_delay_timeout_uses_the_default() {
+        let proxy = |secs| ProxySettings {
+            delay_timeout_secs: secs,
+            ..Default::default()
+        };
+        assert_eq!(proxy(0).delay_timeout(), Duration::from_secs(5));
+        assert_eq!(proxy(8).delay_timeout(), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn bad_engine_section_keeps_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let mut config = AppConfig::default();
+        config.nodes.push(sample_node());
+        let mut value = serde_json::to_value(&config).unwrap();
+        value["engine"] = serde_json::json!({ "outbound_bind_address": "eth0" });
+        std::fs::write(&path, value.to_string()).unwrap();
+
         let store = ConfigStore::load(&path).unwrap();
-        assert!(store.config().nodes.is_empty());
-        assert!(path.with_extension("bad").exists());
+        assert_eq!(store.config().nodes.len(), 1);
+        assert!(store.config().engine.outbound_bind_address.is_none());
+    }
+
+    #[test]
+    fn diff_lists_changed_fields_and_redacts_secrets() {
+        let before = AppConfig::default();
+        let mut after = before.clone();
+        after.mode = Mode::Global;
+        after.proxy.delay_timeout_secs = 8;
+        after.nodes.push(sample_node());
+        after.engine.users.push(crate::inbound::ProxyUser {
+            username: "alice".into(),
+            password: "wonder".into(),
+        });
+
+        let changes = before.diff(&after);
+        let find = |path: &str| changes.iter().find(|c| c.path == path);
+        let mode = find("mode").expect("mode change");
+        assert_eq!(mode.old, Some(Value::from("rule")));
+        assert_eq!(mode.new, Some(Value::from("global")));
+        let timeout = find("proxy.delay_timeout_secs").expect("timeout change");
+        assert_eq!(timeout.new, Some(Value::from(8)));
+
+        // Whole new array elements appear as one change each.
+        let node = find("nodes.0").expect("added node");
+        assert_eq!(node.old, None);
+        let user = find("engine.users.0").expect("added user");
+        assert_eq!(user.new.as_ref().unwrap()["username"], "alice");
+
+        // Secrets never leave the diff, wherever they sit.
+        let text = serde_json::to_string(&changes).unwrap();
+        assert!(!text.contains("wonder"), "{text}");
+        assert!(!text.contains("\"pw\""), "{text}");
+        assert_eq!(changes.len(), 4, "{changes:#?}");
+        assert!(before.diff(&before).is_empty());
+
+        let mut rotated = after.clone();
+        if let NodeConfig::Shadowsocks(ss) = &mut rotated.nodes[0].config {
+            ss.password = "new-pw".into();
+        }
+        let changes = after.diff(&rotated);
+        assert_eq!(
+            changes,
+            vec![ConfigChange {
+                path: "nodes.0.config.password".into(),
+                old: Some(Value::from(REDACTED)),
+                new: Some(Value::from(REDACTED)),
+            }]
+        );
+    }
+
+    #[test]
+    fn import_links_skips_duplicates() {
+        let existing = "ss://YWVzLTI1Ni1nY206cGFzc3dvcmQ@1.2.3.4:8388#old";
+        let mut config = AppConfig::default();
+        assert!(matches!(
+            config.import_links([existing])[..],
+            [LinkImport::Added(_)]
+        ));
+
+        let outcomes = config.import_links([
+            // Same server and credentials as the existing node, new name.
+            "ss://YWVzLTI1Ni1nY206cGFzc3dvcmQ@1.2.3.4:8388#renamed",
+            "trojan://pw@5.6.7.8:443#fresh",
+            // Repeats the line above within the batch.
+            "trojan://pw@5.6.7.8:443#again",
+            "bogus://x",
+        ]);
+        assert!(matches!(&outcomes[0], LinkImport::Duplicate(name) if name == "renamed"));
+        assert!(matches!(&outcomes[1], LinkImport::Added(_)));
+        assert!(matches!(&outcomes[2], LinkImport::Duplicate(name) if name == "again"));
+        assert!(matches!(&outcomes[3], LinkImport::Invalid(_)));
+        let names: Vec<_> = config.nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["old", "fresh"]);
     }
 
     #[test]
@@ -0,0 +1,222 @@
+//! Live connection table: one entry per accepted connection, from accept
+//! until the connection task ends. A debugging view ("what is the proxy
+//! doing right now") plus a per-connection kill switch; never consulted on
+//! the data path.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::stats::Counters;
+
+/// Beyond this many live entries new connections are served but not
+/// listed, so a flood cannot grow the table without bound.
+const MAX_TRACKED: usize = 10_000;
+
+/// One row of [`ConnTable::snapshot`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionInfo {
+    pub id: u64,
+    pub peer: SocketAddr,
+    /// None while the inbound handshake is still running.
+    pub inbound: Option<&'static str>,
+    pub target: Option<String>,
+    pub outbound: Option<String>,
+    pub up: u64,
+    pub down: u64,
+    pub duration_ms: u64,
+}
+
+struct Entry {
+    peer: SocketAddr,
+    started: Instant,
+    session: Option<(&'static str, String, String)>,
+    counters: Arc<Counters>,
+    kill: CancellationToken,
+}
+
+#[derive(Default)]
+pub struct ConnTable {
+    entries: Mutex<HashMap<u64, Entry>>,
+    next_id: AtomicU64,
+}
+
+impl ConnTable {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Add a freshly accepted connection. The entry is removed when the
+    /// returned handle drops, whatever path the connection task exits by.
+    pub fn track(self: &Arc<Self>, peer: SocketAddr) -> TrackedConn {
+        let counters = Arc::new(Counters::default());
+        let kill = CancellationToken::new();
+        let mut entries = self.entries.lock().expect("conntrack poisoned");
+        let id = (entries.len() < MAX_TRACKED).then(|| {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+            entries.insert(
+                id,
+                Entry {
+                    peer,
+                    started: Instant::now(),
+                    session: None,
+                    counters: counters.clone(),
+                    kill: kill.clone(),
+                },
+            );
+            id
+        });
+        TrackedConn {
+            table: self.clone(),
+            id,
+            counters,
+            kill,
+        }
+    }
+
+    /// Ask one connection to close. Returns false for unknown ids
+    /// (already closed, or never listed).
+    pub fn kill(&self, id: u64) -> bool {
+        let entries = self.entries.lock().expect("conntrack poisoned");
+        match entries.get(&id) {
+            Some(entry) => {
+                entry.kill.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// All live connections, oldest first.
+    pub fn snapshot(&self) -> Vec<ConnectionInfo> {
+        let entries = self.entries.lock().expect("conntrack poisoned");
+        let mut out: Vec<ConnectionInfo> = entries
+            .iter()
+            .map(|(&id, e)| {
+                let (up, down) = e.counters.load();
+                let (inbound, target, outbound) = match &e.session {
+                    Some((inbound, target, outbound)) => {
+                        (Some(*inbound), Some(target.clone()), Some(outbound.clone()))
+                    }
+                    None => (None, None, None),
+                };
+                ConnectionInfo {
+                    id,
+                    peer: e.peer,
+                    inbound,
+                    target,
+                    outbound,
+                    up,
+                    down,
+                    duration_ms: e.started.elapsed().as_millis() as u64,
+                }
+            })
+            .collect();
+        out.sort_by_key(|c| c.id);
+        out
+    }
+}
+
+/// A connection's row in the table; removes it on drop.
+pub struct TrackedConn {
+    table: Arc<ConnTable>,
+    /// None when the table was full at accept time.
+    id: Option<u64>,
+    counters: Arc<Counters>,
+    kill: CancellationToken,
+}
+
+impl TrackedConn {
+    /// Resolves once [`ConnTable::kill`] targets this connection.
+    pub async fn killed(&self) {
+        self.kill.cancelled().await
+    }
+
+    /// Record the routed session once the handshake is done.
+    pub fn set_session(&self, inbound: &'static str, target: String, outbound: String) {
+        let Some(id) = self.id else { return };
+        let mut entries = self.table.entries.lock().expect("conntrack poisoned");
+        if let Some(entry) = entries.get_mut(&id) {
+            entry.session = Some((inbound, target, outbound));
+        }
+    }
+
+    /// Byte counters for this connection (fed by the stats wrapper).
+    pub(crate) fn counters(&self) -> Arc<Counters> {
+        self.counters.clone()
+    }
+}
+
+impl Drop for TrackedConn {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.table
+                .entries
+                .lock()
+                .expect("conntrack poisoned")
+                .remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer() -> SocketAddr {
+        "127.0.0.1:40000".parse().unwrap()
+    }
+
+    #[test]
+    fn entries_live_as_long_as_their_handle() {
+        let table = ConnTable::new();
+        let first = table.track(peer());
+        let second = table.track(peer());
+        second.set_session("socks5", "example.com:443".into(), "proxy".into());
+
+        let rows = table.snapshot();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].inbound, None, "still handshaking");
+        assert_eq!(rows[1].target.as_deref(), Some("example.com:443"));
+        assert_eq!(rows[1].outbound.as_deref(), Some("proxy"));
+
+        drop(first);
+        let rows = table.snapshot();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].inbound, Some("socks5"));
+        drop(second);
+        assert!(table.snapshot().is_empty());
+    }
+
+    #[tokio::test]
+    async fn kill_reaches_only_its_connection() {
+        let table = ConnTable::new();
+        let victim = table.track(peer());
+        let bystander = table.track(peer());
+        let id = table.snapshot()[0].id;
+
+        assert!(table.kill(id));
+        tokio::time::timeout(std::time::Duration::from_secs(1), victim.killed())
+            .await
+            .expect("kill must wake the connection");
+        assert!(!bystander.kill.is_cancelled());
+
+        drop(victim);
+        assert!(!table.kill(id), "gone once the task ends");
+    }
+
+    #[test]
+    fn table_is_bounded() {
+        let table = ConnTable::new();
+        let handles: Vec<_> = (0..MAX_TRACKED + 5).map(|_| table.track(peer())).collect();
+        assert_eq!(table.snapshot().len(), MAX_TRACKED);
+        // Untracked handles must not disturb tracked rows on drop.
+        drop(handles);
+        assert!(table.snapshot().is_empty());
+    }
+}
@@ -2,37 +2,45 @@
 //! outbound from the node config (same factory the registry uses), dial
 //! a probe URL, and time the full handshake plus first response bytes.
 //! Independent of the running engine — works with the core stopped and
-//! never perturbs live traffic.
+//! never perturbs live traffic. Also home to the cheaper startup
+//! preflight, which only checks that node servers accept a connection.
 
+use std::net::IpAddr;
 use std::time::{Duration, Instant};
 
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use crate::common::{CoreError, Session, parse_host_port};
-use crate::node::NodeConfig;
+use crate::common::{CoreError, Session, dial, parse_host_port};
+use crate::node::{Node, NodeConfig};
 use crate::outbound::build_outbound;
 
 pub const DEFAULT_PROBE_URL: &str = "http://www.gstatic.com/generate_204";
 pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// Measure full connect + protocol handshake + probe response time.
+/// Measure full connect + protocol handshake + probe response time,
+/// dialing from `bind` when set so the result reflects the real egress.
 pub async fn test_delay(
     node: &NodeConfig,
     probe_url: &str,
     timeout: Duration,
+    bind: Option<IpAddr>,
 ) -> Result<Duration, CoreError> {
-    tokio::time::timeout(timeout, probe(node, probe_url)).await?
+    tokio::time::timeout(timeout, probe(node, probe_url, bind)).await?
 }
 
-async fn probe(node: &NodeConfig, probe_url: &str) -> Result<Duration, CoreError> {
+async fn probe(
+    node: &NodeConfig,
+    probe_url: &str,
+    bind: Option<IpAddr>,
+) -> Result<Duration, CoreError> {
     let (host, port, path) = parse_probe_url(probe_url)?;
     let target = parse_host_port(&host, port);
 
     let outbound = build_outbound(node)?;
+    let mut session = Session::tcp(target, "delay-test");
+    session.bind = bind;
     let start = Instant::now();
-    let mut stream = outbound
-        .dial_tcp(&Session::tcp(target, "delay-test"))
-        .await?;
+    let mut stream = outbound.dial_tcp(&session).await?;
 
     let request = format!(
         "GET {path} HTTP/1.1\r\nHost: {host}:{port}\r\nUser-Agent: vulpini/{}\r\nConnection: close\r\n\r\n",
@@ -101,13 +109,14 @@ pub fn test_all(
     nodes: Vec<(crate::node::NodeId, NodeConfig)>,
     probe_url: String,
     timeout: Duration,
+    bind: Option<IpAddr>,
     concurrency: usize,
 ) -> impl futures::Stream<Item = DelayResult> {
     use futures::StreamExt;
     futures::stream::iter(nodes.into_iter().map(move |(id, config)| {
         let url = probe_url.clone();
         async move {
-            let delay = test_delay(&config, &url, timeout)
+            let delay = test_delay(&config, &url, timeout, bind)
                 .await
                 .map_err(|e| e.to_string());
             DelayResult { node_id: id, delay }
@@ -115,3 +124,91 @@ pub fn test_all(
     }))
     .buffer_unordered(concurrency)
 }
+
+/// Startup preflight outcome: which node servers accepted a TCP connect.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreflightSummary {
+    /// Node names.
+    pub reachable: Vec<String>,
+    /// Node names with the connect error.
+    pub unreachable: Vec<(String, String)>,
+}
+
+/// TCP-connect to every node's server (bounded concurrency, from `bind`
+/// when set, like live traffic) — no protocol handshake, so it is a quick
+/// "is anything out there" check before listening, not a substitute for
+/// [`test_delay`].
+pub async fn preflight(
+    nodes: &[Node],
+    timeout: Duration,
+    bind: Option<IpAddr>,
+    concurrency: usize,
+) -> PreflightSummary {
+    use futures::StreamExt;
+    let checks = nodes.iter().map(|node| async move {
+        let connect = dial::tcp_connect((node.config.server(), node.config.port()), bind);
+        let result = match tokio::time::timeout(timeout, connect).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(CoreError::Timeout.to_string()),
+        };
+        (node.name.clone(), result)
+    });
+    let mut results = futures::stream::iter(checks).buffer_unordered(concurrency);
+    let mut summary = PreflightSummary::default();
+    while let Some((name, result)) = results.next().await {
+        match result {
+            Ok(()) => summary.reachable.push(name),
+            Err(e) => summary.unreachable.push((name, e)),
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{NodeSource, SsConfig, SsMethod};
+
+    fn node(name: &str, port: u16) -> Node {
+        Node::new(
+            name.into(),
+            NodeSource::Manual,
+            NodeConfig::Shadowsocks(SsConfig {
+                server: "127.0.0.1".into(),
+                port,
+                method: SsMethod::Aes256Gcm,
+                password: "pw".into(),
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn preflight_splits_reachable_from_unreachable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().port();
+        // Bound then dropped: nothing listens there any more.
+        let closed = {
+            let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            l.local_addr().unwrap().port()
+        };
+
+        let nodes = [node("up", open), node("down", closed)];
+        let summary = preflight(&nodes, Duration::from_secs(2), None, 8).await;
+        assert_eq!(summary.reachable, vec!["up".to_string()]);
+        assert_eq!(summary.unreachable.len(), 1);
+        assert_eq!(summary.unreachable[0].0, "down");
+    }
+
+    #[tokio::test]
+    async fn preflight_dials_from_the_bind_address() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let bind: IpAddr = "127.0.0.2".parse().unwrap();
+
+        let summary = preflight(&[node("up", port)], Duration::from_secs(2), Some(bind), 8).await;
+        assert_eq!(summary.reachable, vec!["up".to_string()]);
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer.ip(), bind);
+    }
+}
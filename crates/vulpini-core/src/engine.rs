@@ -1,48 +1,194 @@
-use std::net::SocketAddr;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{Mutex, broadcast};
 use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 
+use crate::common::throttle::ThrottledStream;
 use crate::common::{BoxedStream, CoreError, Session};
-use crate::inbound::{self, InboundKind};
+use crate::conntrack::{ConnTable, ConnectionInfo, TrackedConn};
+use crate::inbound::{self, InboundKind, ProxyUser};
 use crate::outbound::OutboundRegistry;
 use crate::relay::relay;
 use crate::router::Router;
-use crate::stats::{CoreEvent, StatsRegistry, StatsSnapshot};
+use crate::stats::{CoreEvent, InboundStats, RunSummary, StatsRegistry, StatsSnapshot};
 
 const DRAIN_GRACE: Duration = Duration::from_secs(5);
 const EVENT_CAPACITY: usize = 64;
 const TICK_INTERVAL: Duration = Duration::from_secs(1);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// First pause after a failed accept; doubles per consecutive failure.
+const ACCEPT_BACKOFF_INITIAL: Duration = Duration::from_millis(5);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+/// Floor for the pause after EMFILE/ENFILE: retrying sooner just spins,
+/// since nothing frees a descriptor until live connections close.
+const FD_EXHAUSTED_BACKOFF: Duration = Duration::from_secs(1);
+const FD_EXHAUSTED_LOG_INTERVAL: Duration = Duration::from_secs(30);
+/// Every tunnel holds two descriptors (client and upstream), so a soft
+/// limit below this caps the engine at a few hundred concurrent tunnels.
+const FD_LIMIT_WARN_BELOW: u64 = 1024;
+
+/// Engine tuning knobs (the "engine" section of the config file). Not
+/// part of the Settings UI; hot-swappable via [`EngineHandle::set_config`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EngineConfig {
+    /// Warn when connection setup (inbound handshake + routing + dial)
+    /// takes longer than this. None disables the check.
+    #[serde(default)]
+    pub slow_request_threshold_ms: Option<u64>,
+    /// Fraction (0.0..=1.0) of accepted connections logged at info level,
+    /// as a low-volume activity heartbeat. None or 0 logs none.
+    #[serde(default)]
+    pub accept_log_sample_rate: Option<f64>,
+    /// Cap for the exponential pause between consecutive failed accepts.
+    /// None or 0 uses the built-in 1s — a zero cap would busy-loop.
+    #[serde(default)]
+    pub accept_backoff_max_ms: Option<u64>,
+    /// Inbound proxy credentials (SOCKS5 and HTTP alike). Empty, the
+    /// default, leaves the inbound open. Authenticated clients can reach
+    /// this host's loopback and LAN unless a block rule covers them.
+    #[serde(default)]
+    pub users: Vec<ProxyUser>,
+    /// Per-tunnel bandwidth cap in kilobits per second, applied to each
+    /// direction separately. None or 0 leaves tunnels unthrottled.
+    #[serde(default)]
+    pub max_bandwidth_kbps: Option<u64>,
+    /// Deadline for a client to complete its inbound handshake (protocol
+    /// detection through the CONNECT request). None or 0 uses the built-in
+    /// 10s — a zero deadline would drop every client before its first byte.
+    #[serde(default)]
+    pub handshake_timeout_secs: Option<u64>,
+    /// How long shutdown waits for live tunnels to finish on their own
+    /// before aborting them. None or 0 uses the built-in 5s.
+    #[serde(default)]
+    pub shutdown_grace_secs: Option<u64>,
+    /// Local source address for proxied connections, delay tests and the
+    /// preflight, to direct targets and proxy servers alike (multi-homed
+    /// hosts). Subscription and geo downloads do not use it. None lets
+    /// the OS pick.
+    #[serde(default)]
+    pub outbound_bind_address: Option<IpAddr>,
+}
+
+impl EngineConfig {
+    /// True when a setup that took `elapsed` should be reported as slow.
+    pub fn is_slow(&self, elapsed: Duration) -> bool {
+        self.slow_request_threshold_ms
+            .is_some_and(|ms| elapsed > Duration::from_millis(ms))
+    }
+
+    /// Whether the `n`th accepted connection (1-based) gets an accept log.
+    /// Deterministic rather than random: exactly `floor(n * rate)` of the
+    /// first `n` connections are logged, evenly spread.
+    pub fn samples_accept(&self, n: u64) -> bool {
+        let rate = match self.accept_log_sample_rate {
+            Some(rate) if rate > 0.0 => rate.min(1.0),
+            _ => return false,
+        };
+        let quota = |k: u64| (k as f64 * rate).floor() as u64;
+        n > 0 && quota(n) > quota(n - 1)
+    }
+
+    /// The per-direction tunnel cap in bytes per second, if any.
+    fn bandwidth_cap(&self) -> Option<u64> {
+        self.max_bandwidth_kbps
+            .filter(|&kbps| kbps > 0)
+            .map(|kbps| kbps.saturating_mul(1000) / 8)
+    }
+
+    fn handshake_timeout(&self) -> Duration {
+        self.handshake_timeout_secs
+            .filter(|&secs| secs > 0)
+            .map_or(HANDSHAKE_TIMEOUT, Duration::from_secs)
+    }
+
+    fn shutdown_grace(&self) -> Duration {
+        self.shutdown_grace_secs
+            .filter(|&secs| secs > 0)
+            .map_or(DRAIN_GRACE, Duration::from_secs)
+    }
+
+    fn accept_backoff_max(&self) -> Duration {
+        self.accept_backoff_max_ms
+            .filter(|&ms| ms > 0)
+            .map_or(ACCEPT_BACKOFF_MAX, Duration::from_millis)
+    }
+}
+
+/// Per-inbound accept switches. A paused inbound keeps the shared listener
+/// bound (the port is never released) but refuses every new session of its
+/// protocol with a protocol-level error reply.
+#[derive(Default)]
+struct InboundGates {
+    socks5: AtomicBool,
+    socks4: AtomicBool,
+    http: AtomicBool,
+}
+
+impl InboundGates {
+    fn flag(&self, kind: InboundKind) -> &AtomicBool {
+        match kind {
+            InboundKind::Socks5 => &self.socks5,
+            InboundKind::Socks4 => &self.socks4,
+            InboundKind::Http => &self.http,
+        }
+    }
+
+    fn is_paused(&self, kind: InboundKind) -> bool {
+        self.flag(kind).load(Ordering::Relaxed)
+    }
+}
+
+/// Engine state shared by the handle, the accept loop and every
+/// connection task. Hot-swappable parts sit behind `ArcSwap`.
+struct Shared {
+    registry: Arc<OutboundRegistry>,
+    router: ArcSwap<Router>,
+    config: ArcSwap<EngineConfig>,
+    gates: InboundGates,
+    /// Engine-wide gate above the per-inbound ones: refuse every new
+    /// session, whatever its protocol.
+    maintenance: AtomicBool,
+    /// Connections accepted since start; drives accept-log sampling.
+    accepted: AtomicU64,
+    stats: Arc<StatsRegistry>,
+    conntrack: Arc<ConnTable>,
+}
 
 /// A running engine: owns the listener task and all live connection tasks.
 /// Dropping it does nothing — call [`EngineHandle::shutdown`].
 pub struct EngineHandle {
     local_addr: SocketAddr,
-    router: Arc<ArcSwap<Router>>,
+    started_at: Instant,
+    shared: Arc<Shared>,
     shutdown: CancellationToken,
     accept_task: tokio::task::JoinHandle<()>,
     tick_task: tokio::task::JoinHandle<()>,
     conns: Arc<Mutex<JoinSet<()>>>,
     events_tx: broadcast::Sender<CoreEvent>,
-    stats: Arc<StatsRegistry>,
 }
 
 impl EngineHandle {
-    /// Start the engine with the given router. Routing decisions are made
-    /// per session; swapping the router later takes effect immediately.
+    /// Start the engine with the given router and knobs. Routing decisions
+    /// are made per session; swapping the router later takes effect
+    /// immediately. `config` is in force from the first accept — inbound
+    /// credentials must never have an open window.
     pub async fn start(
         listen: SocketAddr,
         registry: Arc<OutboundRegistry>,
         router: Router,
+        config: EngineConfig,
     ) -> Result<Self, CoreError> {
         let listener = TcpListener::bind(listen).await?;
-        Self::from_listener(listener, registry, router).await
+        Self::from_listener(listener, registry, router, config).await
     }
 
     /// Start with port fallback: try `listen`, then the next few ports,
@@ -54,6 +200,7 @@ impl EngineHandle {
         listen: SocketAddr,
         registry: Arc<OutboundRegistry>,
         router: Router,
+        config: EngineConfig,
     ) -> Result<Self, CoreError> {
         let mut first_err: Option<CoreError> = None;
         for offset in 0u16..=2 {
@@ -62,7 +209,9 @@ impl EngineHandle {
             };
             let candidate = SocketAddr::new(listen.ip(), port);
             match TcpListener::bind(candidate).await {
-                Ok(listener) => return Self::from_listener(listener, registry, router).await,
+                Ok(listener) => {
+                    return Self::from_listener(listener, registry, router, config).await;
+                }
                 Err(e) => {
                     warn!(addr = %candidate, error = %e, "listen address unavailable");
                     if first_err.is_none() {
@@ -77,7 +226,7 @@ impl EngineHandle {
             Ok(listener) => {
                 let actual = listener.local_addr()?;
                 warn!(addr = %actual, "falling back to an OS-assigned port");
-                Self::from_listener(listener, registry, router).await
+                Self::from_listener(listener, registry, router, config).await
             }
             Err(e) => Err(first_err.unwrap_or_else(|| e.into())),
         }
@@ -87,38 +236,46 @@ impl EngineHandle {
         listener: TcpListener,
         registry: Arc<OutboundRegistry>,
         router: Router,
+        config: EngineConfig,
     ) -> Result<Self, CoreError> {
         let local_addr = listener.local_addr()?;
         let shutdown = CancellationToken::new();
         let conns: Arc<Mutex<JoinSet<()>>> = Arc::new(Mutex::new(JoinSet::new()));
-        let router = Arc::new(ArcSwap::from_pointee(router));
-        let stats = StatsRegistry::new();
+        let shared = Arc::new(Shared {
+            registry,
+            router: ArcSwap::from_pointee(router),
+            config: ArcSwap::from_pointee(config),
+            gates: InboundGates::default(),
+            maintenance: AtomicBool::new(false),
+            accepted: AtomicU64::new(0),
+            stats: StatsRegistry::new(),
+            conntrack: ConnTable::new(),
+        });
         let (events_tx, _) = broadcast::channel(EVENT_CAPACITY);
 
         let accept_task = tokio::spawn(accept_loop(
             listener,
-            registry,
-            router.clone(),
-            stats.clone(),
+            shared.clone(),
             shutdown.clone(),
             conns.clone(),
         ));
         let tick_task = tokio::spawn(tick_loop(
-            stats.clone(),
+            shared.stats.clone(),
             events_tx.clone(),
             shutdown.clone(),
         ));
 
         info!(%local_addr, "engine listening");
+        check_fd_limit();
         Ok(Self {
             local_addr,
-            router,
+            started_at: Instant::now(),
+            shared,
             shutdown,
             accept_task,
             tick_task,
             conns,
             events_tx,
-            stats,
         })
     }
 
@@ -133,29 +290,96 @@ impl EngineHandle {
 
     /// One-shot stats pull (for initial UI paint).
     pub fn stats_snapshot(&self) -> StatsSnapshot {
-        self.stats.snapshot()
+        self.shared.stats.snapshot()
     }
 
     /// Hot-swap the router (mode or rule changes). In-flight connections
     /// keep their already-dialed outbounds; new sessions use the new rules.
     pub fn set_router(&self, router: Router) {
-        self.router.store(Arc::new(router));
+        self.shared.router.store(Arc::new(router));
+    }
+
+    /// Hot-swap the engine knobs. Like [`EngineHandle::set_router`], each
+    /// session reads the config once when it is accepted.
+    pub fn set_config(&self, config: EngineConfig) {
+        self.shared.config.store(Arc::new(config));
+    }
+
+    /// Refuse new sessions of one inbound protocol while the other keeps
+    /// working; live connections are untouched. Returns false when the
+    /// inbound was already paused.
+    pub fn pause_inbound(&self, kind: InboundKind) -> bool {
+        !self.shared.gates.flag(kind).swap(true, Ordering::Relaxed)
+    }
+
+    /// Undo [`EngineHandle::pause_inbound`]. Returns false when the inbound
+    /// was not paused.
+    pub fn resume_inbound(&self, kind: InboundKind) -> bool {
+        self.shared.gates.flag(kind).swap(false, Ordering::Relaxed)
     }
 
-    /// Stop accepting, drain live connections with a grace period, then
-    /// abort whatever remains. Idempotent-ish: consumes the handle.
-    pub async fn shutdown(self) {
+    pub fn is_inbound_paused(&self, kind: InboundKind) -> bool {
+        self.shared.gates.is_paused(kind)
+    }
+
+    /// Maintenance mode: keep running (and keep the port) but refuse every
+    /// new session until switched off. Unlike [`EngineHandle::shutdown`],
+    /// live connections are left alone. Returns false when already in the
+    /// requested state.
+    pub fn set_maintenance(&self, on: bool) -> bool {
+        let changed = self.shared.maintenance.swap(on, Ordering::Relaxed) != on;
+        if changed {
+            info!(on, "maintenance mode");
+        }
+        changed
+    }
+
+    pub fn is_maintenance(&self) -> bool {
+        self.shared.maintenance.load(Ordering::Relaxed)
+    }
+
+    /// Live connections, oldest first: peer, target, outbound, bytes so
+    /// far. Connections still in their handshake have no target yet.
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        self.shared.conntrack.snapshot()
+    }
+
+    /// Lifetime sessions and bytes per inbound protocol.
+    pub fn inbound_stats(&self) -> BTreeMap<String, InboundStats> {
+        self.shared.stats.by_inbound()
+    }
+
+    /// Close one live connection by its [`ConnectionInfo::id`]. Returns
+    /// false when no such connection is listed.
+    pub fn kill_connection(&self, id: u64) -> bool {
+        let killed = self.shared.conntrack.kill(id);
+        if killed {
+            info!(id, "connection killed on request");
+        }
+        killed
+    }
+
+    /// Stop accepting, give live connections the configured grace period
+    /// (`shutdown_grace_secs`) to finish, then abort whatever remains.
+    /// Consumes the handle. Returns a summary of the whole run, logged only
+    /// at debug since the CLI prints it itself.
+    pub async fn shutdown(self) -> RunSummary {
         self.shutdown.cancel();
         let _ = self.accept_task.await;
         let _ = self.tick_task.await;
 
+        let grace = self.shared.config.load().shutdown_grace();
         let mut conns = self.conns.lock().await;
         let drain = async { while conns.join_next().await.is_some() {} };
-        if tokio::time::timeout(DRAIN_GRACE, drain).await.is_err() {
+        if tokio::time::timeout(grace, drain).await.is_err() {
             warn!("drain timed out, aborting live connections");
             conns.abort_all();
         }
+        let summary = self.shared.stats.summary(self.started_at.elapsed());
+        let json = serde_json::to_string(&summary).unwrap_or_default();
         info!("engine stopped");
+        debug!(summary = %json, "run summary");
+        summary
     }
 }
 
@@ -185,56 +409,242 @@ async fn tick_loop(
 
 async fn accept_loop(
     listener: TcpListener,
-    registry: Arc<OutboundRegistry>,
-    router: Arc<ArcSwap<Router>>,
-    stats: Arc<StatsRegistry>,
+    shared: Arc<Shared>,
     token: CancellationToken,
     conns: Arc<Mutex<JoinSet<()>>>,
 ) {
+    let mut backoff = AcceptBackoff::default();
+    let mut fd_logged: Option<Instant> = None;
+    let mut spare_fd = SpareFd::reserve();
     loop {
         tokio::select! {
             _ = token.cancelled() => break,
             accept = listener.accept() => match accept {
-                Ok((stream, _peer)) => {
-                    let registry = registry.clone();
-                    let router = router.clone();
-                    let stats = stats.clone();
+                Ok((stream, peer)) => {
+                    backoff.reset();
+                    let n = shared.accepted.fetch_add(1, Ordering::Relaxed) + 1;
+                    if shared.config.load().samples_accept(n) {
+                        info!(%peer, accepted = n, "connection accepted");
+                    }
+                    let shared = shared.clone();
                     conns.lock().await.spawn(async move {
-                        stats.conn_open();
-                        if let Err(e) = handle_connection(stream, &registry, &router, &stats).await {
-                            debug!(error = %e, "connection closed with error");
+                        let _active = shared.stats.connection();
+                        let tracked = shared.conntrack.track(peer);
+                        // Dropping the handler future on kill closes both
+                        // the client and the upstream stream.
+                        tokio::select! {
+                            result = handle_connection(stream, peer, &shared, &tracked) => {
+                                if let Err(e) = result {
+                                    debug!(error = %e, "connection closed with error");
+                                }
+                            }
+                            _ = tracked.killed() => debug!(%peer, "connection killed"),
                         }
-                        stats.conn_close();
                     });
                 }
+                Err(e) if is_fd_exhaustion(&e) => {
+                    if fd_logged.is_none_or(|t| t.elapsed() >= FD_EXHAUSTED_LOG_INTERVAL) {
+                        let limits = fd_limits();
+                        error!(
+                            error = %e,
+                            open_fds = ?spare_fd.open_fds(),
+                            soft_limit = ?limits.map(|(soft, _)| soft),
+                            hard_limit = ?limits.map(|(_, hard)| hard),
+                            "out of file descriptors, pausing accepts"
+                        );
+                        fd_logged = Some(Instant::now());
+                    }
+                    let delay = backoff
+                        .next_delay(shared.config.load().accept_backoff_max())
+                        .max(FD_EXHAUSTED_BACKOFF);
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                }
                 Err(e) => {
-                    warn!(error = %e, "accept failed");
-                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    let delay = backoff.next_delay(shared.config.load().accept_backoff_max());
+                    warn!(error = %e, backoff_ms = delay.as_millis() as u64, "accept failed");
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        _ = tokio::time::sleep(delay) => {}
+                    }
                 }
             },
         }
     }
 }
 
+/// Exponential backoff between consecutive failed accepts, so a persistent
+/// error costs a handful of wakeups per second instead of a busy loop.
+/// Reset by the next successful accept.
+#[derive(Default)]
+struct AcceptBackoff {
+    failures: u32,
+}
+
+impl AcceptBackoff {
+    fn next_delay(&mut self, max: Duration) -> Duration {
+        let delay = ACCEPT_BACKOFF_INITIAL
+            .checked_mul(1 << self.failures.min(20))
+            .map_or(max, |d| d.min(max));
+        self.failures = self.failures.saturating_add(1);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+/// Winsock's "too many open sockets"; not in std or libc.
+#[cfg(windows)]
+const WSAEMFILE: i32 = 10024;
+
+/// EMFILE / ENFILE (per-process / system-wide descriptor limit), or
+/// WSAEMFILE on Windows.
+fn is_fd_exhaustion(e: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    const CODES: &[i32] = &[libc::EMFILE, libc::ENFILE];
+    #[cfg(windows)]
+    const CODES: &[i32] = &[WSAEMFILE];
+    #[cfg(not(any(unix, windows)))]
+    const CODES: &[i32] = &[];
+    e.raw_os_error().is_some_and(|code| CODES.contains(&code))
+}
+
+/// One descriptor held back for diagnostics: listing /proc/self/fd needs
+/// a free descriptor, which is exactly what EMFILE says there is none of.
+struct SpareFd(Option<std::fs::File>);
+
+impl SpareFd {
+    fn reserve() -> Self {
+        SpareFd(open_spare())
+    }
+
+    /// Release the spare, count, then take it back.
+    fn open_fds(&mut self) -> Option<usize> {
+        self.0 = None;
+        let count = open_fd_count();
+        self.0 = open_spare();
+        count
+    }
+}
+
+fn open_spare() -> Option<std::fs::File> {
+    #[cfg(unix)]
+    {
+        std::fs::File::open("/dev/null").ok()
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// RLIMIT_NOFILE as (soft, hard). Needs no descriptor, unlike the count.
+fn fd_limits() -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: getrlimit only writes into the struct it is handed.
+        let rc = unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) };
+        // rlim_t is u64 on Linux and macOS but signed on some BSDs.
+        #[allow(clippy::unnecessary_cast)]
+        (rc == 0).then_some((limit.rlim_cur as u64, limit.rlim_max as u64))
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Log the descriptor limit once at start, loudly when it is low enough
+/// to be the first thing a busy engine runs into.
+fn check_fd_limit() {
+    match fd_limits() {
+        Some((soft, hard)) if soft < FD_LIMIT_WARN_BELOW => warn!(
+            soft,
+            hard,
+            max_tunnels = soft / 2,
+            "low file descriptor limit; raise it (ulimit -n) for heavy use"
+        ),
+        Some((soft, hard)) => info!(soft, hard, "file descriptor limit"),
+        None => {}
+    }
+}
+
+/// Descriptors currently open by this process, where cheaply knowable.
+/// Itself needs a free descriptor; see [`SpareFd`].
+fn open_fd_count() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_dir("/proc/self/fd")
+            .ok()
+            .map(|dir| dir.count())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
 async fn handle_connection(
     stream: TcpStream,
-    registry: &OutboundRegistry,
-    router: &ArcSwap<Router>,
-    stats: &StatsRegistry,
+    peer: SocketAddr,
+    shared: &Shared,
+    tracked: &TrackedConn,
 ) -> Result<(), CoreError> {
+    let started = Instant::now();
+    let config = shared.config.load_full();
     stream.set_nodelay(true).ok();
-    let kind = inbound::detect(&stream).await?;
-    let mut stream: BoxedStream = Box::pin(stream);
-
-    let (target, tag) = match kind {
-        InboundKind::Socks5 => (inbound::socks5::handshake(&mut stream).await?, "socks5"),
-        InboundKind::Http => (inbound::http::handshake(&mut stream).await?, "http"),
+    // A client that connects and stalls must not hold a task forever.
+    let handshake = async {
+        let kind = inbound::detect(&stream).await?;
+        let mut stream: BoxedStream = Box::pin(stream);
+        let request = match kind {
+            InboundKind::Socks5 => {
+                inbound::socks5::handshake(&mut stream, peer, &config.users).await?
+            }
+            InboundKind::Socks4 => inbound::socks4::handshake(&mut stream, &config.users).await?,
+            InboundKind::Http => inbound::http::handshake(&mut stream, &config.users).await?,
+        };
+        Ok::<_, CoreError>((kind, stream, request))
     };
-    let session = Session::tcp(target, tag);
-    let route = router.load().route(&session);
-    debug!(target = %session.target, inbound = tag, outbound = %route, "session");
+    let (kind, mut stream, request) =
+        tokio::time::timeout(config.handshake_timeout(), handshake).await??;
+    let tag = kind.tag();
+    // Checked after the handshake so the refusal is a proper reply (503 /
+    // general failure) rather than a reset mid-request.
+    let refusal = if shared.maintenance.load(Ordering::Relaxed) {
+        Some("engine in maintenance".to_string())
+    } else if shared.gates.is_paused(kind) {
+        Some(format!("{tag} inbound paused"))
+    } else {
+        None
+    };
+    if let Some(reason) = refusal {
+        let e = CoreError::Unavailable(reason);
+        inbound::reply_err(&mut stream, kind, &e).await.ok();
+        return Err(e);
+    }
+    let mut session = Session::tcp(request.target, tag);
+    session.user = request.user;
+    session.bind = config.outbound_bind_address;
+    let route = shared.router.load().route(&session);
+    debug!(
+        target = %session.target,
+        inbound = tag,
+        user = session.user.as_deref(),
+        outbound = %route,
+        "session"
+    );
+    tracked.set_session(tag, session.target.to_string(), route.clone());
 
-    let outbound = registry.get(&route)?;
+    let outbound = shared.registry.get(&route)?;
     let upstream = match outbound.dial_tcp(&session).await {
         Ok(upstream) => upstream,
         Err(e) => {
@@ -244,6 +654,130 @@ async fn handle_connection(
     };
     inbound::reply_ok(&mut stream, kind).await?;
 
-    relay(stream, stats.wrap(&route, upstream)).await?;
+    let setup = started.elapsed();
+    if config.is_slow(setup) {
+        warn!(
+            target = %session.target,
+            inbound = tag,
+            outbound = %route,
+            setup_ms = setup.as_millis() as u64,
+            "slow connection setup"
+        );
+    }
+
+    let upstream = match config.bandwidth_cap() {
+        Some(bytes_per_sec) => ThrottledStream::wrap(upstream, bytes_per_sec),
+        None => upstream,
+    };
+    let upstream = shared
+        .stats
+        .wrap_session(&route, tag, upstream, tracked.counters());
+    relay(stream, upstream).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fd_exhaustion_is_recognized() {
+        assert!(!is_fd_exhaustion(&std::io::Error::from(
+            std::io::ErrorKind::ConnectionAborted
+        )));
+        #[cfg(unix)]
+        {
+            assert!(is_fd_exhaustion(&std::io::Error::from_raw_os_error(
+                libc::EMFILE
+            )));
+            assert!(is_fd_exhaustion(&std::io::Error::from_raw_os_error(
+                libc::ENFILE
+            )));
+        }
+        #[cfg(windows)]
+        assert!(is_fd_exhaustion(&std::io::Error::from_raw_os_error(
+            WSAEMFILE
+        )));
+    }
+
+    #[test]
+    fn accept_backoff_grows_caps_and_resets() {
+        let max = Duration::from_millis(100);
+        let mut backoff = AcceptBackoff::default();
+        let delays: Vec<u64> = (0..7)
+            .map(|_| backoff.next_delay(max).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [5, 10, 20, 40, 80, 100, 100]);
+
+        // Many failures in a row must not overflow.
+        for _ in 0..100 {
+            assert_eq!(backoff.next_delay(max), max);
+        }
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(max), ACCEPT_BACKOFF_INITIAL);
+
+        // A zero cap in the config is unset, not "never pause".
+        let zero = EngineConfig {
+            accept_backoff_max_ms: Some(0),
+            ..Default::default()
+        }
+        .accept_backoff_max();
+        assert_eq!(zero, ACCEPT_BACKOFF_MAX);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(zero), ACCEPT_BACKOFF_INITIAL);
+    }
+
+    #[test]
+    fn accept_sampling_follows_rate() {
+        let config = |rate| EngineConfig {
+            accept_log_sample_rate: rate,
+            ..Default::default()
+        };
+        let logged = |c: &EngineConfig| (1..=1000).filter(|&n| c.samples_accept(n)).count();
+        assert_eq!(logged(&config(None)), 0);
+        assert_eq!(logged(&config(Some(0.0))), 0);
+        assert_eq!(logged(&config(Some(0.1))), 100);
+        assert_eq!(logged(&config(Some(1.0))), 1000);
+        assert_eq!(logged(&config(Some(5.0))), 1000, "clamped to 1.0");
+    }
+
+    #[test]
+    fn zero_handshake_timeout_uses_the_default() {
+        let config = |secs| EngineConfig {
+            handshake_timeout_secs: secs,
+            ..Default::default()
+        };
+        assert_eq!(config(None).handshake_timeout(), HANDSHAKE_TIMEOUT);
+        assert_eq!(config(Some(0)).handshake_timeout(), HANDSHAKE_TIMEOUT);
+        assert_eq!(config(Some(3)).handshake_timeout(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn zero_shutdown_grace_uses_the_default() {
+        let config = |secs| EngineConfig {
+            shutdown_grace_secs: secs,
+            ..Default::default()
+        };
+        assert_eq!(config(None).shutdown_grace(), DRAIN_GRACE);
+        assert_eq!(config(Some(0)).shutdown_grace(), DRAIN_GRACE);
+        assert_eq!(config(Some(3)).shutdown_grace(), Duration::from_secs(3));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn open_fds_are_counted() {
+        assert!(open_fd_count().is_some_and(|n| n > 0));
+        let mut spare = SpareFd::reserve();
+        assert!(spare.0.is_some());
+        assert!(spare.open_fds().is_some_and(|n| n > 0));
+        assert!(spare.0.is_some(), "spare is taken back after counting");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fd_limits_are_read() {
+        let (soft, hard) = fd_limits().expect("getrlimit");
+        assert!(soft > 0 && soft <= hard);
+    }
+}
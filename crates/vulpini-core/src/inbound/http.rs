@@ -1,6 +1,9 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use crate::common::{Address, BoxedStream, CoreError, parse_host_port};
+use super::{InboundRequest, ProxyUser, authenticate};
+use crate::common::{BoxedStream, CoreError, parse_host_port};
 
 pub const TAG: &str = "http";
 
@@ -9,7 +12,11 @@ const OK_RESPONSE: &[u8] = b"HTTP/1.1 200 Connection established\r\n\r\n";
 
 /// Read an HTTP CONNECT request. Only CONNECT is supported — plain
 /// forward-proxy requests are rejected (use the SOCKS5 port instead).
-pub async fn handshake(stream: &mut BoxedStream) -> Result<Address, CoreError> {
+/// With `users` non-empty, `Proxy-Authorization: Basic` is required.
+pub async fn handshake(
+    stream: &mut BoxedStream,
+    users: &[ProxyUser],
+) -> Result<InboundRequest, CoreError> {
     let header = read_until_header_end(stream).await?;
     let text = String::from_utf8(header)
         .map_err(|_| CoreError::Protocol("CONNECT header is not utf-8".into()))?;
@@ -34,8 +41,50 @@ pub async fn handshake(stream: &mut BoxedStream) -> Result<Address, CoreError> {
         )));
     }
 
+    let user = if users.is_empty() {
+        None
+    } else {
+        match check_proxy_auth(&text, users) {
+            Ok(user) => Some(user),
+            Err(e) => {
+                reply_err(stream, &e).await.ok();
+                return Err(e);
+            }
+        }
+    };
+
     let (host, port) = split_authority(authority)?;
-    Ok(parse_host_port(&host, port))
+    Ok(InboundRequest {
+        target: parse_host_port(&host, port),
+        user,
+    })
+}
+
+/// Validate the `Proxy-Authorization: Basic` header against `users`.
+fn check_proxy_auth(header: &str, users: &[ProxyUser]) -> Result<String, CoreError> {
+    let value = header
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("proxy-authorization"))
+        .map(|(_, value)| value.trim())
+        .ok_or_else(|| CoreError::Auth("missing Proxy-Authorization".into()))?;
+    // Auth schemes are case-insensitive (RFC 7235 §2.1).
+    let encoded = value
+        .split_once(' ')
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("basic"))
+        .map(|(_, encoded)| encoded)
+        .ok_or_else(|| CoreError::Auth("only Basic proxy auth is supported".into()))?;
+    let decoded = STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|raw| String::from_utf8(raw).ok())
+        .ok_or_else(|| CoreError::Auth("malformed Basic credentials".into()))?;
+    let (username, password) = decoded
+        .split_once(':')
+        .ok_or_else(|| CoreError::Auth("malformed Basic credentials".into()))?;
+    authenticate(users, username, password)
+        .ok_or_else(|| CoreError::Auth(format!("bad credentials for '{username}'")))
 }
 
 fn split_authority(authority: &str) -> Result<(String, u16), CoreError> {
@@ -80,9 +129,19 @@ pub async fn reply_ok(stream: &mut BoxedStream) -> Result<(), CoreError> {
 }
 
 pub async fn reply_err(stream: &mut BoxedStream, err: &CoreError) -> Result<(), CoreError> {
+    if let CoreError::Auth(_) = err {
+        let body = "HTTP/1.1 407 Proxy Authentication Required\r\n\
+                    Proxy-Authenticate: Basic realm=\"vulpini\"\r\n\
+                    Content-Length: 0\r\n\r\n";
+        stream.write_all(body.as_bytes()).await?;
+        stream.flush().await?;
+        return Ok(());
+    }
     let (code, reason) = match err {
         CoreError::Blocked => (403, "Forbidden"),
         CoreError::Unsupported(_) => (405, "Method Not Allowed"),
+        CoreError::Unavailable(_) => (503, "Service Unavailable"),
+        CoreError::Timeout => (504, "Gateway Timeout"),
         _ => (502, "Bad Gateway"),
     };
     let body = format!("HTTP/1.1 {code} {reason}\r\nContent-Length: 0\r\n\r\n");
@@ -94,6 +153,7 @@ pub async fn reply_err(stream: &mut BoxedStream, err: &CoreError) -> Result<(),
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::Address;
     use tokio::io::duplex;
 
     #[tokio::test]
@@ -110,7 +170,7 @@ mod tests {
                 .unwrap();
         });
 
-        let addr = handshake(&mut server).await.unwrap();
+        let addr = handshake(&mut server, &[]).await.unwrap().target;
         assert_eq!(addr, Address::Domain("example.com".into(), 443));
         writer.await.unwrap();
     }
@@ -129,7 +189,7 @@ mod tests {
                 .unwrap();
         });
 
-        let addr = handshake(&mut server).await.unwrap();
+        let addr = handshake(&mut server, &[]).await.unwrap().target;
         assert_eq!(
             addr,
             "[::1]:8080".parse::<std::net::SocketAddr>().unwrap().into()
@@ -155,9 +215,85 @@ mod tests {
         });
 
         assert!(matches!(
-            handshake(&mut server).await,
+            handshake(&mut server, &[]).await,
             Err(CoreError::Unsupported(_))
         ));
         writer.await.unwrap();
     }
+
+    fn users() -> Vec<ProxyUser> {
+        vec![ProxyUser {
+            username: "alice".into(),
+            password: "wonder:land".into(),
+        }]
+    }
+
+    #[test]
+    fn proxy_auth_header_checked() {
+        let ok = format!(
+            "CONNECT a:1 HTTP/1.1\r\nproxy-authorization: Basic {}\r\n\r\n",
+            STANDARD.encode("alice:wonder:land")
+        );
+        assert_eq!(check_proxy_auth(&ok, &users()).unwrap(), "alice");
+        let shouted = ok.replace("Basic", "BASIC");
+        assert_eq!(check_proxy_auth(&shouted, &users()).unwrap(), "alice");
+
+        let wrong = format!(
+            "CONNECT a:1 HTTP/1.1\r\nProxy-Authorization: Basic {}\r\n\r\n",
+            STANDARD.encode("alice:wonder")
+        );
+        for header in [
+            wrong.as_str(),
+            "CONNECT a:1 HTTP/1.1\r\n\r\n",
+            "CONNECT a:1 HTTP/1.1\r\nProxy-Authorization: Bearer x\r\n\r\n",
+            "CONNECT a:1 HTTP/1.1\r\nProxy-Authorization: Basic !!!\r\n\r\n",
+        ] {
+            assert!(matches!(
+                check_proxy_auth(header, &users()),
+                Err(CoreError::Auth(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_credentials_get_407() {
+        let (client, server) = duplex(2048);
+        let mut server: BoxedStream = Box::pin(server);
+        let mut client = client;
+
+        let writer = tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            client
+                .write_all(b"CONNECT example.com:443 HTTP/1.1\r\n\r\n")
+                .await
+                .unwrap();
+            let mut buf = vec![0u8; 256];
+            let n = client.read(&mut buf).await.unwrap();
+            let reply = String::from_utf8_lossy(&buf[..n]).to_string();
+            assert!(reply.starts_with("HTTP/1.1 407"), "got: {reply}");
+            assert!(reply.contains("Proxy-Authenticate: Basic"), "got: {reply}");
+        });
+
+        assert!(matches!(
+            handshake(&mut server, &users()).await,
+            Err(CoreError::Auth(_))
+        ));
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn dial_timeout_gets_504() {
+        use tokio::io::AsyncReadExt;
+
+        let (mut client, server) = duplex(256);
+        let mut server: BoxedStream = Box::pin(server);
+        reply_err(&mut server, &CoreError::Timeout).await.unwrap();
+        let mut buf = vec![0u8; 256];
+        let n = client.read(&mut buf).await.unwrap();
+        let reply = String::from_utf8_lossy(&buf[..n]);
+        assert!(
+            reply.starts_with("HTTP/1.1 504 Gateway Timeout"),
+            "got: {reply}"
+        );
+    }
 }
@@ -1,18 +1,88 @@
 pub mod http;
+pub mod socks4;
 pub mod socks5;
 
+use serde::{Deserialize, Serialize};
 use tokio::net::TcpStream;
 
-use crate::common::{BoxedStream, CoreError};
+use crate::common::{Address, BoxedStream, CoreError};
+
+/// One credential accepted by the inbound (SOCKS5 username/password,
+/// HTTP `Proxy-Authorization: Basic`). An empty user list disables auth.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyUser {
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for ProxyUser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyUser")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Look up a username/password pair; returns the matched username.
+/// Every entry is checked in constant time, so response timing reveals
+/// neither which user exists nor how much of a password was right.
+fn authenticate(users: &[ProxyUser], username: &str, password: &str) -> Option<String> {
+    let mut matched = None;
+    for user in users {
+        let ok = constant_time_eq(user.username.as_bytes(), username.as_bytes())
+            & constant_time_eq(user.password.as_bytes(), password.as_bytes());
+        if ok && matched.is_none() {
+            matched = Some(user.username.clone());
+        }
+    }
+    matched
+}
+
+/// Byte equality whose running time depends only on the lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+/// What an inbound handshake yields: where the client wants to go and,
+/// with auth on, who it authenticated as.
+#[derive(Debug)]
+pub struct InboundRequest {
+    pub target: Address,
+    pub user: Option<String>,
+}
 
 /// Which protocol an accepted connection speaks. The mixed inbound serves
-/// both on one port: SOCKS5 starts with 0x05, HTTP CONNECT with ASCII.
+/// all on one port: SOCKS5 starts with 0x05, SOCKS4/4a with 0x04, HTTP
+/// CONNECT with ASCII.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InboundKind {
     Socks5,
+    Socks4,
     Http,
 }
 
+impl InboundKind {
+    pub const ALL: [InboundKind; 3] = [InboundKind::Socks5, InboundKind::Socks4, InboundKind::Http];
+
+    /// The session tag ("socks5" / "socks4" / "http").
+    pub fn tag(&self) -> &'static str {
+        match self {
+            InboundKind::Socks5 => socks5::TAG,
+            InboundKind::Socks4 => socks4::TAG,
+            InboundKind::Http => http::TAG,
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.tag() == tag)
+    }
+}
+
 /// Peek at the first byte without consuming it and pick the protocol.
 pub async fn detect(stream: &TcpStream) -> Result<InboundKind, CoreError> {
     let mut byte = [0u8; 1];
@@ -22,16 +92,17 @@ pub async fn detect(stream: &TcpStream) -> Result<InboundKind, CoreError> {
             "connection closed before greeting".into(),
         ));
     }
-    Ok(if byte[0] == 0x05 {
-        InboundKind::Socks5
-    } else {
-        InboundKind::Http
+    Ok(match byte[0] {
+        0x05 => InboundKind::Socks5,
+        0x04 => InboundKind::Socks4,
+        _ => InboundKind::Http,
     })
 }
 
 pub async fn reply_ok(stream: &mut BoxedStream, kind: InboundKind) -> Result<(), CoreError> {
     match kind {
         InboundKind::Socks5 => socks5::reply_ok(stream).await,
+        InboundKind::Socks4 => socks4::reply_ok(stream).await,
         InboundKind::Http => http::reply_ok(stream).await,
     }
 }
@@ -43,6 +114,34 @@ pub async fn reply_err(
 ) -> Result<(), CoreError> {
     match kind {
         InboundKind::Socks5 => socks5::reply_err(stream, err).await,
+        InboundKind::Socks4 => socks4::reply_err(stream, err).await,
         InboundKind::Http => http::reply_err(stream, err).await,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authenticate_matches_whole_pairs_only() {
+        let users = vec![
+            ProxyUser {
+                username: "alice".into(),
+                password: "wonder".into(),
+            },
+            ProxyUser {
+                username: "bob".into(),
+                password: "builder".into(),
+            },
+        ];
+        assert_eq!(
+            authenticate(&users, "bob", "builder").as_deref(),
+            Some("bob")
+        );
+        assert_eq!(authenticate(&users, "alice", "builder"), None);
+        assert_eq!(authenticate(&users, "alice", "wonde"), None);
+        assert_eq!(authenticate(&users, "alice", "wonderland"), None);
+        assert_eq!(authenticate(&users, "carol", "wonder"), None);
+    }
+}
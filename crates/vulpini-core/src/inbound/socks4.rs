@@ -0,0 +1,175 @@
+//! SOCKS4 and SOCKS4a (CONNECT only) for legacy clients sharing the mixed
+//! port. SOCKS4 carries no password, so it is refused whenever inbound
+//! auth is configured.
+
+use std::net::Ipv4Addr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use super::{InboundRequest, ProxyUser};
+use crate::common::{Address, BoxedStream, CoreError, parse_host_port};
+
+pub const TAG: &str = "socks4";
+
+const VER: u8 = 0x04;
+const CMD_CONNECT: u8 = 0x01;
+/// Reply version byte is zero, not 4.
+const REPLY_VER: u8 = 0x00;
+const REP_GRANTED: u8 = 0x5A;
+const REP_REJECTED: u8 = 0x5B;
+/// Upper bound for the NUL-terminated USERID and SOCKS4a hostname.
+const MAX_FIELD: usize = 255;
+
+/// Read a SOCKS4/4a request. A destination of 0.0.0.x (x != 0) means
+/// SOCKS4a: the hostname follows the user id.
+pub async fn handshake(
+    stream: &mut BoxedStream,
+    users: &[ProxyUser],
+) -> Result<InboundRequest, CoreError> {
+    // VER CMD DSTPORT(2) DSTIP(4) USERID NUL
+    let mut head = [0u8; 8];
+    stream.read_exact(&mut head).await?;
+    if head[0] != VER {
+        return Err(CoreError::Protocol(format!(
+            "bad socks version {:#x}",
+            head[0]
+        )));
+    }
+    let port = u16::from_be_bytes([head[2], head[3]]);
+    let ip = Ipv4Addr::new(head[4], head[5], head[6], head[7]);
+    let _user_id = read_nul_terminated(stream).await?;
+
+    let target = match ip.octets() {
+        [0, 0, 0, x] if x != 0 => {
+            let host = read_nul_terminated(stream).await?;
+            if host.is_empty() {
+                return Err(CoreError::Protocol("empty socks4a host".into()));
+            }
+            parse_host_port(&host, port)
+        }
+        _ => Address::from((ip, port)),
+    };
+
+    if head[1] != CMD_CONNECT {
+        reply(stream, REP_REJECTED).await.ok();
+        return Err(CoreError::Unsupported(format!(
+            "socks4 command {:#x} not supported",
+            head[1]
+        )));
+    }
+    if !users.is_empty() {
+        reply(stream, REP_REJECTED).await.ok();
+        return Err(CoreError::Auth(
+            "socks4 cannot authenticate; use socks5 or http".into(),
+        ));
+    }
+    Ok(InboundRequest { target, user: None })
+}
+
+async fn read_nul_terminated(stream: &mut BoxedStream) -> Result<String, CoreError> {
+    let mut field = Vec::new();
+    loop {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).await?;
+        if byte[0] == 0 {
+            break;
+        }
+        if field.len() == MAX_FIELD {
+            return Err(CoreError::Protocol("socks4 field too long".into()));
+        }
+        field.push(byte[0]);
+    }
+    String::from_utf8(field).map_err(|_| CoreError::Protocol("socks4 field is not utf-8".into()))
+}
+
+async fn reply(stream: &mut BoxedStream, rep: u8) -> Result<(), CoreError> {
+    // DSTPORT/DSTIP are ignored by clients for CONNECT.
+    let pkt = [REPLY_VER, rep, 0, 0, 0, 0, 0, 0];
+    stream.write_all(&pkt).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+pub async fn reply_ok(stream: &mut BoxedStream) -> Result<(), CoreError> {
+    reply(stream, REP_GRANTED).await
+}
+
+/// SOCKS4 has a single failure code for everything.
+pub async fn reply_err(stream: &mut BoxedStream, _err: &CoreError) -> Result<(), CoreError> {
+    reply(stream, REP_REJECTED).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::duplex;
+
+    async fn run(request: &'static [u8], users: &[ProxyUser]) -> Result<InboundRequest, CoreError> {
+        let (mut client, server) = duplex(1024);
+        let mut server: BoxedStream = Box::pin(server);
+        client.write_all(request).await.unwrap();
+        handshake(&mut server, users).await
+    }
+
+    #[tokio::test]
+    async fn socks4_ipv4() {
+        let request = run(b"\x04\x01\x01\xbb\x01\x02\x03\x04user\x00", &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            request.target,
+            "1.2.3.4:443"
+                .parse::<std::net::SocketAddr>()
+                .unwrap()
+                .into()
+        );
+    }
+
+    #[tokio::test]
+    async fn socks4a_domain() {
+        let request = run(b"\x04\x01\x00\x50\x00\x00\x00\x01\x00example.com\x00", &[])
+            .await
+            .unwrap();
+        assert_eq!(request.target, Address::Domain("example.com".into(), 80));
+    }
+
+    #[tokio::test]
+    async fn bind_rejected() {
+        let (mut client, server) = duplex(1024);
+        let mut server: BoxedStream = Box::pin(server);
+        client
+            .write_all(b"\x04\x02\x00\x50\x01\x02\x03\x04\x00")
+            .await
+            .unwrap();
+        let err = handshake(&mut server, &[]).await.unwrap_err();
+        assert!(matches!(err, CoreError::Unsupported(_)));
+        let mut rep = [0u8; 8];
+        client.read_exact(&mut rep).await.unwrap();
+        assert_eq!(rep[..2], [REPLY_VER, REP_REJECTED]);
+    }
+
+    #[tokio::test]
+    async fn refused_when_auth_configured() {
+        let users = [ProxyUser {
+            username: "alice".into(),
+            password: "wonder".into(),
+        }];
+        let err = run(b"\x04\x01\x00\x50\x01\x02\x03\x04alice\x00", &users)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Auth(_)));
+    }
+
+    #[tokio::test]
+    async fn overlong_user_id_rejected() {
+        let (mut client, server) = duplex(4096);
+        let mut server: BoxedStream = Box::pin(server);
+        client
+            .write_all(b"\x04\x01\x00\x50\x01\x02\x03\x04")
+            .await
+            .unwrap();
+        client.write_all(&[b'a'; 300]).await.unwrap();
+        let err = handshake(&mut server, &[]).await.unwrap_err();
+        assert!(matches!(err, CoreError::Protocol(_)));
+    }
+}
@@ -1,12 +1,22 @@
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::warn;
 
+use super::{InboundRequest, ProxyUser, authenticate};
 use crate::common::{Address, BoxedStream, CoreError, parse_host_port};
 
 pub const TAG: &str = "socks5";
 
 const VER: u8 = 0x05;
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_GSSAPI: u8 = 0x01;
+const METHOD_USER_PASS: u8 = 0x02;
+const METHOD_NONE_ACCEPTABLE: u8 = 0xFF;
+/// RFC 1929 subnegotiation version and status codes.
+const AUTH_VER: u8 = 0x01;
+const AUTH_SUCCESS: u8 = 0x00;
+const AUTH_FAILURE: u8 = 0x01;
 const CMD_CONNECT: u8 = 0x01;
 const ATYP_V4: u8 = 0x01;
 const ATYP_DOMAIN: u8 = 0x03;
@@ -15,11 +25,21 @@ const ATYP_V6: u8 = 0x04;
 const REP_SUCCESS: u8 = 0x00;
 const REP_GENERAL_FAILURE: u8 = 0x01;
 const REP_NOT_ALLOWED: u8 = 0x02;
+const REP_NETWORK_UNREACHABLE: u8 = 0x03;
+const REP_HOST_UNREACHABLE: u8 = 0x04;
+const REP_CONN_REFUSED: u8 = 0x05;
+const REP_TTL_EXPIRED: u8 = 0x06;
 const REP_CMD_NOT_SUPPORTED: u8 = 0x07;
 
-/// Read the SOCKS5 greeting + request. Returns the target address.
-/// No authentication is offered (local inbound only).
-pub async fn handshake(stream: &mut BoxedStream) -> Result<Address, CoreError> {
+/// Read the SOCKS5 greeting + request. With `users` empty only no-auth is
+/// offered (the default, local inbound); otherwise username/password
+/// (RFC 1929) is required, and picked whatever else the client offers.
+/// `peer` only names the client in the log.
+pub async fn handshake(
+    stream: &mut BoxedStream,
+    peer: SocketAddr,
+    users: &[ProxyUser],
+) -> Result<InboundRequest, CoreError> {
     // Greeting: VER NMETHODS METHODS...
     let mut head = [0u8; 2];
     stream.read_exact(&mut head).await?;
@@ -35,13 +55,31 @@ pub async fn handshake(stream: &mut BoxedStream) -> Result<Address, CoreError> {
     }
     let mut methods = vec![0u8; nmethods];
     stream.read_exact(&mut methods).await?;
-    if !methods.contains(&0x00) {
-        stream.write_all(&[VER, 0xFF]).await.ok();
-        return Err(CoreError::Protocol(
-            "client does not offer no-auth method".into(),
-        ));
-    }
-    stream.write_all(&[VER, 0x00]).await?;
+    let user = if users.is_empty() {
+        if !methods.contains(&METHOD_NO_AUTH) {
+            stream.write_all(&[VER, METHOD_NONE_ACCEPTABLE]).await.ok();
+            return Err(CoreError::Protocol(
+                "client does not offer no-auth method".into(),
+            ));
+        }
+        stream.write_all(&[VER, METHOD_NO_AUTH]).await?;
+        None
+    } else {
+        if !methods.contains(&METHOD_USER_PASS) {
+            stream.write_all(&[VER, METHOD_NONE_ACCEPTABLE]).await.ok();
+            // GSSAPI is the only other auth method clients send in
+            // practice; name it so the refusal is clear in the log.
+            let reason = if methods.contains(&METHOD_GSSAPI) {
+                warn!(%peer, "refusing socks5 client: GSSAPI is not supported");
+                "client offers GSSAPI, which is not supported; username/password is required"
+            } else {
+                "client does not offer username/password"
+            };
+            return Err(CoreError::Auth(reason.into()));
+        }
+        stream.write_all(&[VER, METHOD_USER_PASS]).await?;
+        Some(user_pass_auth(stream, users).await?)
+    };
 
     // Request: VER CMD RSV ATYP DST.ADDR DST.PORT
     let mut req = [0u8; 4];
@@ -59,7 +97,40 @@ pub async fn handshake(stream: &mut BoxedStream) -> Result<Address, CoreError> {
     }
 
     let target = read_address(stream, req[3]).await?;
-    Ok(target)
+    Ok(InboundRequest { target, user })
+}
+
+/// RFC 1929: VER ULEN UNAME PLEN PASSWD, answered with VER STATUS.
+async fn user_pass_auth(
+    stream: &mut BoxedStream,
+    users: &[ProxyUser],
+) -> Result<String, CoreError> {
+    let mut head = [0u8; 2];
+    stream.read_exact(&mut head).await?;
+    if head[0] != AUTH_VER {
+        return Err(CoreError::Protocol(format!(
+            "bad auth version {:#x}",
+            head[0]
+        )));
+    }
+    let mut username = vec![0u8; head[1] as usize];
+    stream.read_exact(&mut username).await?;
+    let mut plen = [0u8; 1];
+    stream.read_exact(&mut plen).await?;
+    let mut password = vec![0u8; plen[0] as usize];
+    stream.read_exact(&mut password).await?;
+
+    let username = String::from_utf8_lossy(&username);
+    match authenticate(users, &username, &String::from_utf8_lossy(&password)) {
+        Some(user) => {
+            stream.write_all(&[AUTH_VER, AUTH_SUCCESS]).await?;
+            Ok(user)
+        }
+        None => {
+            stream.write_all(&[AUTH_VER, AUTH_FAILURE]).await.ok();
+            Err(CoreError::Auth(format!("bad credentials for '{username}'")))
+        }
+    }
 }
 
 async fn read_address(stream: &mut BoxedStream, atyp: u8) -> Result<Address, CoreError> {
@@ -115,11 +186,25 @@ pub async fn reply_ok(stream: &mut BoxedStream) -> Result<(), CoreError> {
 
 /// Map an engine error to the closest SOCKS5 reply code and report it.
 pub async fn reply_err(stream: &mut BoxedStream, err: &CoreError) -> Result<(), CoreError> {
-    let rep = match err {
+    reply(stream, rep_for(err)).await
+}
+
+/// The RFC 1928 REP code for `err`. Dial failures keep their cause
+/// (refused / unreachable / timed out) so clients can tell them apart.
+fn rep_for(err: &CoreError) -> u8 {
+    use std::io::ErrorKind;
+    match err {
         CoreError::Blocked => REP_NOT_ALLOWED,
+        CoreError::Timeout => REP_TTL_EXPIRED,
+        CoreError::Io(e) => match e.kind() {
+            ErrorKind::ConnectionRefused => REP_CONN_REFUSED,
+            ErrorKind::NetworkUnreachable => REP_NETWORK_UNREACHABLE,
+            ErrorKind::HostUnreachable => REP_HOST_UNREACHABLE,
+            ErrorKind::TimedOut => REP_TTL_EXPIRED,
+            _ => REP_GENERAL_FAILURE,
+        },
         _ => REP_GENERAL_FAILURE,
-    };
-    reply(stream, rep).await
+    }
 }
 
 #[cfg(test)]
@@ -131,6 +216,10 @@ mod tests {
         Box::pin(s)
     }
 
+    fn peer() -> SocketAddr {
+        "127.0.0.1:50000".parse().unwrap()
+    }
+
     #[tokio::test]
     async fn handshake_domain() {
         let (client, server) = duplex(1024);
@@ -150,7 +239,7 @@ mod tests {
             client.write_all(&443u16.to_be_bytes()).await.unwrap();
         });
 
-        let addr = handshake(&mut server).await.unwrap();
+        let addr = handshake(&mut server, peer(), &[]).await.unwrap().target;
         assert_eq!(addr, Address::Domain("example.com".into(), 443));
         writer.await.unwrap();
     }
@@ -172,7 +261,7 @@ mod tests {
             client.write_all(&80u16.to_be_bytes()).await.unwrap();
         });
 
-        let addr = handshake(&mut server).await.unwrap();
+        let addr = handshake(&mut server, peer(), &[]).await.unwrap().target;
         assert_eq!(
             addr,
             "1.2.3.4:80".parse::<std::net::SocketAddr>().unwrap().into()
@@ -180,6 +269,24 @@ mod tests {
         writer.await.unwrap();
     }
 
+    #[test]
+    fn dial_failures_keep_their_reply_code() {
+        use std::io::{Error, ErrorKind};
+        let io = |kind| CoreError::Io(Error::from(kind));
+        assert_eq!(rep_for(&io(ErrorKind::ConnectionRefused)), REP_CONN_REFUSED);
+        assert_eq!(
+            rep_for(&io(ErrorKind::NetworkUnreachable)),
+            REP_NETWORK_UNREACHABLE
+        );
+        assert_eq!(
+            rep_for(&io(ErrorKind::HostUnreachable)),
+            REP_HOST_UNREACHABLE
+        );
+        assert_eq!(rep_for(&CoreError::Timeout), REP_TTL_EXPIRED);
+        assert_eq!(rep_for(&CoreError::Blocked), REP_NOT_ALLOWED);
+        assert_eq!(rep_for(&io(ErrorKind::Other)), REP_GENERAL_FAILURE);
+    }
+
     #[tokio::test]
     async fn udp_associate_rejected() {
         let (client, server) = duplex(1024);
@@ -199,8 +306,187 @@ mod tests {
             assert_eq!(rep[1], REP_CMD_NOT_SUPPORTED);
         });
 
-        let err = handshake(&mut server).await.unwrap_err();
+        let err = handshake(&mut server, peer(), &[]).await.unwrap_err();
         assert!(matches!(err, CoreError::Unsupported(_)));
         writer.await.unwrap();
     }
+
+    fn users() -> Vec<ProxyUser> {
+        vec![
+            ProxyUser {
+                username: "alice".into(),
+                password: "wonder".into(),
+            },
+            ProxyUser {
+                username: "bob".into(),
+                password: "builder".into(),
+            },
+        ]
+    }
+
+    /// Greeting offering `methods`, then (if the server picks user/pass)
+    /// the RFC 1929 subnegotiation. Returns (selected method, auth status).
+    async fn client_auth(
+        client: &mut BoxedStream,
+        methods: &[u8],
+        username: &str,
+        password: &str,
+    ) -> (u8, Option<u8>) {
+        let mut greeting = vec![0x05, methods.len() as u8];
+        greeting.extend_from_slice(methods);
+        client.write_all(&greeting).await.unwrap();
+        let mut sel = [0u8; 2];
+        client.read_exact(&mut sel).await.unwrap();
+        if sel[1] != METHOD_USER_PASS {
+            return (sel[1], None);
+        }
+        let mut auth = vec![AUTH_VER, username.len() as u8];
+        auth.extend_from_slice(username.as_bytes());
+        auth.push(password.len() as u8);
+        auth.extend_from_slice(password.as_bytes());
+        client.write_all(&auth).await.unwrap();
+        let mut status = [0u8; 2];
+        client.read_exact(&mut status).await.unwrap();
+        (sel[1], Some(status[1]))
+    }
+
+    #[tokio::test]
+    async fn user_pass_accepts_any_listed_user() {
+        let (client, server) = duplex(1024);
+        let mut server = boxed(server);
+        let mut client = boxed(client);
+
+        let writer = tokio::spawn(async move {
+            let (method, status) = client_auth(
+                &mut client,
+                &[METHOD_NO_AUTH, METHOD_USER_PASS],
+                "bob",
+                "builder",
+            )
+            .await;
+            assert_eq!((method, status), (METHOD_USER_PASS, Some(AUTH_SUCCESS)));
+            client
+                .write_all(&[0x05, 0x01, 0x00, 0x01, 1, 2, 3, 4, 0, 80])
+                .await
+                .unwrap();
+        });
+
+        let request = handshake(&mut server, peer(), &users()).await.unwrap();
+        assert_eq!(request.user.as_deref(), Some("bob"));
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn user_pass_rejects_bad_password() {
+        let (client, server) = duplex(1024);
+        let mut server = boxed(server);
+        let mut client = boxed(client);
+
+        let writer = tokio::spawn(async move {
+            let (_, status) = client_auth(&mut client, &[METHOD_USER_PASS], "alice", "bob's").await;
+            assert_eq!(status, Some(AUTH_FAILURE));
+        });
+
+        let err = handshake(&mut server, peer(), &users()).await.unwrap_err();
+        assert!(matches!(err, CoreError::Auth(_)));
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn no_auth_client_refused_when_users_configured() {
+        let (client, server) = duplex(1024);
+        let mut server = boxed(server);
+        let mut client = boxed(client);
+
+        let writer = tokio::spawn(async move {
+            let (method, _) = client_auth(&mut client, &[METHOD_NO_AUTH], "", "").await;
+            assert_eq!(method, METHOD_NONE_ACCEPTABLE);
+        });
+
+        let err = handshake(&mut server, peer(), &users()).await.unwrap_err();
+        assert!(matches!(err, CoreError::Auth(_)));
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn user_pass_preferred_over_gssapi() {
+        let (client, server) = duplex(1024);
+        let mut server = boxed(server);
+        let mut client = boxed(client);
+
+        let writer = tokio::spawn(async move {
+            let (method, status) = client_auth(
+                &mut client,
+                &[METHOD_GSSAPI, METHOD_USER_PASS],
+                "alice",
+                "wonder",
+            )
+            .await;
+            assert_eq!((method, status), (METHOD_USER_PASS, Some(AUTH_SUCCESS)));
+            client
+                .write_all(&[0x05, 0x01, 0x00, 0x01, 1, 2, 3, 4, 0, 80])
+                .await
+                .unwrap();
+        });
+
+        let request = handshake(&mut server, peer(), &users()).await.unwrap();
+        assert_eq!(request.user.as_deref(), Some("alice"));
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn gssapi_only_client_refused() {
+        let (client, server) = duplex(1024);
+        let mut server = boxed(server);
+        let mut client = boxed(client);
+
+        let writer = tokio::spawn(async move {
+            let (method, _) = client_auth(&mut client, &[METHOD_GSSAPI], "", "").await;
+            assert_eq!(method, METHOD_NONE_ACCEPTABLE);
+        });
+
+        let err = handshake(&mut server, peer(), &users()).await.unwrap_err();
+        assert!(
+            matches!(&err, CoreError::Auth(reason) if reason.contains("GSSAPI")),
+            "{err}"
+        );
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn handshake_survives_one_byte_writes() {
+        let (client, server) = duplex(1024);
+        let mut server = boxed(server);
+        let mut client = boxed(client);
+
+        let domain = "a-rather-long-subdomain.of.some.example.com";
+        let writer = tokio::spawn(async move {
+            let mut greeting = Vec::new();
+            greeting.extend_from_slice(&[0x05, 0x01, METHOD_USER_PASS]);
+            let mut auth = vec![AUTH_VER, 5];
+            auth.extend_from_slice(b"alice");
+            auth.push(6);
+            auth.extend_from_slice(b"wonder");
+            let mut req = vec![0x05, 0x01, 0x00, 0x03, domain.len() as u8];
+            req.extend_from_slice(domain.as_bytes());
+            req.extend_from_slice(&8443u16.to_be_bytes());
+
+            // Every field split across writes, with the server's replies
+            // read between phases exactly as a real client would.
+            for (phase, reply_len) in [(greeting, 2), (auth, 2), (req, 0)] {
+                for byte in phase {
+                    client.write_all(&[byte]).await.unwrap();
+                    client.flush().await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+                let mut reply = vec![0u8; reply_len];
+                client.read_exact(&mut reply).await.unwrap();
+            }
+        });
+
+        let request = handshake(&mut server, peer(), &users()).await.unwrap();
+        assert_eq!(request.target, Address::Domain(domain.into(), 8443));
+        assert_eq!(request.user.as_deref(), Some("alice"));
+        writer.await.unwrap();
+    }
 }
@@ -6,6 +6,7 @@
 
 pub mod common;
 pub mod config;
+pub mod conntrack;
 pub mod delay;
 pub mod engine;
 pub mod geo;
@@ -20,7 +21,7 @@ pub mod transport;
 
 pub use common::{Address, BoxedStream, CoreError, Network, Session};
 pub use config::{AppConfig, ConfigStore};
-pub use engine::EngineHandle;
+pub use engine::{EngineConfig, EngineHandle};
 pub use node::{Node, NodeConfig, NodeId};
 pub use outbound::{Outbound, OutboundRegistry};
 pub use router::{Mode, Router};
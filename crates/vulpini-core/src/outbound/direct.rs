@@ -1,8 +1,8 @@
 use std::time::Duration;
 
 use async_trait::async_trait;
-use tokio::net::TcpStream;
 
+use crate::common::dial::tcp_connect;
 use crate::common::{BoxedStream, CoreError, Session};
 use crate::outbound::{Outbound, TAG_DIRECT};
 
@@ -32,11 +32,12 @@ impl Outbound for DirectOutbound {
 
     async fn dial_tcp(&self, sess: &Session) -> Result<BoxedStream, CoreError> {
         let target = sess.target.clone();
+        let bind = sess.bind;
         let stream = tokio::time::timeout(CONNECT_TIMEOUT, async move {
             match &target {
-                crate::common::Address::Ip(addr) => TcpStream::connect(*addr).await,
+                crate::common::Address::Ip(addr) => tcp_connect(*addr, bind).await,
                 crate::common::Address::Domain(host, port) => {
-                    TcpStream::connect((host.as_str(), *port)).await
+                    tcp_connect((host.as_str(), *port), bind).await
                 }
             }
         })
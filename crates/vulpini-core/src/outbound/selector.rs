@@ -30,7 +30,8 @@ impl Selector {
         selector
     }
 
-    /// Atomically switch the active node.
+    /// Atomically switch the active node. Only later dials see it: a
+    /// tunnel stays on the node it was dialed through until it closes.
     pub fn set(&self, outbound: Arc<dyn Outbound>) {
         self.inner.store(Some(Arc::new(Slot(outbound))));
     }
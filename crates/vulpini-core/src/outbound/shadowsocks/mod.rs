@@ -7,6 +7,7 @@ use async_trait::async_trait;
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
 
+use crate::common::dial::tcp_connect;
 use crate::common::{BoxedStream, CoreError, Session};
 use crate::node::SsConfig;
 use crate::outbound::Outbound;
@@ -50,7 +51,7 @@ impl Outbound for ShadowsocksOutbound {
     async fn dial_tcp(&self, sess: &Session) -> Result<BoxedStream, CoreError> {
         let tcp = tokio::time::timeout(
             CONNECT_TIMEOUT,
-            TcpStream::connect((self.config.server.as_str(), self.config.port)),
+            tcp_connect((self.config.server.as_str(), self.config.port), sess.bind),
         )
         .await??;
         tcp.set_nodelay(true).ok();
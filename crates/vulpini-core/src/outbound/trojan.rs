@@ -47,7 +47,7 @@ impl Outbound for TrojanOutbound {
             allow_insecure: self.config.allow_insecure,
         });
         let mut stream = transport
-            .connect(&self.config.server, self.config.port)
+            .connect(&self.config.server, self.config.port, sess.bind)
             .await?;
 
         let mut header = Vec::with_capacity(56 + 2 + 1 + 1 + 255 + 2 + 2);
@@ -71,7 +71,7 @@ impl Outbound for VlessOutbound {
     async fn dial_tcp(&self, sess: &Session) -> Result<BoxedStream, CoreError> {
         let mut stream = self
             .transport()
-            .connect(&self.config.server, self.config.port)
+            .connect(&self.config.server, self.config.port, sess.bind)
             .await?;
         let header = self.encode_header(&sess.target);
         stream.write_all(&header).await?;
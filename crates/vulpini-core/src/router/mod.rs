@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use vulpini_rules::GeoDb;
 
 use crate::common::Session;
-use crate::outbound::{TAG_DIRECT, TAG_PROXY};
+use crate::outbound::{TAG_BLOCK, TAG_DIRECT, TAG_PROXY};
 pub use rule::{RouteRule, Rule, RuleParseError};
 
 /// Routing mode: Global (everything via the selected node), Direct
@@ -56,23 +56,28 @@ impl Router {
 
     pub fn route(&self, session: &Session) -> String {
         // Private/loopback targets never leave the machine, in every mode.
+        // An authenticated session may come from another host, though, so
+        // for it the first matching rule can still block the local network.
         if session.target.is_private_or_loopback() {
+            if session.user.is_some() && self.first_match(session) == Some(TAG_BLOCK) {
+                return TAG_BLOCK.to_string();
+            }
             return TAG_DIRECT.to_string();
         }
         match self.mode {
             Mode::Global => TAG_PROXY.to_string(),
             Mode::Direct => TAG_DIRECT.to_string(),
-            Mode::Rule => {
-                for rule in &self.rules {
-                    if rule.rule.matches_with(&session.target, self.geo.as_deref()) {
-                        return rule.target.clone();
-                    }
-                }
-                // No MATCH rule and nothing hit: stay safe, stay direct.
-                TAG_DIRECT.to_string()
-            }
+            // No MATCH rule and nothing hit: stay safe, stay direct.
+            Mode::Rule => self.first_match(session).unwrap_or(TAG_DIRECT).to_string(),
         }
     }
+
+    fn first_match(&self, session: &Session) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| rule.rule.matches_with(&session.target, self.geo.as_deref()))
+            .map(|rule| rule.target.as_str())
+    }
 }
 
 /// Placeholder tag set used until the geo/rule data milestones.
@@ -126,6 +131,34 @@ mod tests {
         assert_eq!(router.route(&session("192.168.1.1", 443)), TAG_DIRECT);
     }
 
+    #[test]
+    fn block_rules_guard_private_targets_for_authenticated_sessions() {
+        let router = Router::from_config(
+            Mode::Global,
+            &[
+                "IP-CIDR,192.168.1.10/32,direct".to_string(),
+                "IP-CIDR,192.168.0.0/16,block".to_string(),
+                "IP-CIDR,127.0.0.0/8,block".to_string(),
+                "MATCH,proxy".to_string(),
+            ],
+        )
+        .unwrap();
+        let authed = |host: &str| {
+            let mut s = session(host, 80);
+            s.user = Some("alice".into());
+            s
+        };
+
+        // Local clients (no auth configured) keep the unconditional bypass.
+        assert_eq!(router.route(&session("127.0.0.1", 80)), TAG_DIRECT);
+        assert_eq!(router.route(&authed("127.0.0.1")), TAG_BLOCK);
+        assert_eq!(router.route(&authed("192.168.3.4")), TAG_BLOCK);
+        // First match wins, as for any other target.
+        assert_eq!(router.route(&authed("192.168.1.10")), TAG_DIRECT);
+        assert_eq!(router.route(&authed("10.0.0.1")), TAG_DIRECT);
+        assert_eq!(router.route(&authed("example.com")), TAG_PROXY);
+    }
+
     #[test]
     fn rule_mode_without_match_defaults_direct() {
         let router =
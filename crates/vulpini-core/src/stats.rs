@@ -1,12 +1,13 @@
-//! Traffic accounting: counting streams, per-tag counters, and 1 Hz
-//! snapshot ticks on the engine's event bus.
+//! Traffic accounting: counting streams, per-tag and per-inbound counters,
+//! and 1 Hz snapshot ticks on the engine's event bus.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
@@ -22,32 +23,71 @@ pub struct StatsSnapshot {
     pub active_connections: u32,
 }
 
+/// What one engine run did, returned by `EngineHandle::shutdown`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RunSummary {
+    pub uptime_secs: u64,
+    pub connections_total: u64,
+    pub peak_connections: u64,
+    pub total_up: u64,
+    pub total_down: u64,
+}
+
+/// Lifetime totals for one inbound protocol.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct InboundStats {
+    /// Tunnels established (handshake done, upstream dialed).
+    pub sessions: u64,
+    pub total_up: u64,
+    pub total_down: u64,
+}
+
 #[derive(Debug, Clone)]
 pub enum CoreEvent {
     Stats(StatsSnapshot),
 }
 
-struct Counters {
+#[derive(Default)]
+pub(crate) struct Counters {
     up: AtomicU64,
     down: AtomicU64,
 }
 
-/// Byte counters for the whole engine and per outbound tag.
+impl Counters {
+    /// (up, down) byte totals.
+    pub(crate) fn load(&self) -> (u64, u64) {
+        (
+            self.up.load(Ordering::Relaxed),
+            self.down.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[derive(Default)]
+struct InboundEntry {
+    bytes: Arc<Counters>,
+    sessions: u64,
+}
+
+/// Byte counters for the whole engine, per outbound tag and per inbound.
 pub struct StatsRegistry {
     global: Arc<Counters>,
     per_tag: Mutex<HashMap<String, Arc<Counters>>>,
+    per_inbound: Mutex<HashMap<&'static str, InboundEntry>>,
     active_connections: AtomicU64,
+    connections_total: AtomicU64,
+    peak_connections: AtomicU64,
 }
 
 impl StatsRegistry {
     pub fn new() -> Arc<Self> {
         Arc::new(StatsRegistry {
-            global: Arc::new(Counters {
-                up: AtomicU64::new(0),
-                down: AtomicU64::new(0),
-            }),
+            global: Arc::new(Counters::default()),
             per_tag: Mutex::new(HashMap::new()),
+            per_inbound: Mutex::new(HashMap::new()),
             active_connections: AtomicU64::new(0),
+            connections_total: AtomicU64::new(0),
+            peak_connections: AtomicU64::new(0),
         })
     }
 
@@ -56,21 +96,20 @@ impl StatsRegistry {
             .lock()
             .expect("stats poisoned")
             .entry(tag.to_string())
-            .or_insert_with(|| {
-                Arc::new(Counters {
-                    up: AtomicU64::new(0),
-                    down: AtomicU64::new(0),
-                })
-            })
+            .or_insert_with(|| Arc::new(Counters::default()))
             .clone()
     }
 
-    pub fn conn_open(&self) {
-        self.active_connections.fetch_add(1, Ordering::Relaxed);
-    }
-
-    pub fn conn_close(&self) {
-        self.active_connections.fetch_sub(1, Ordering::Relaxed);
+    /// Count one connection as active until the guard drops. Holding a
+    /// guard (rather than pairing open/close calls) keeps the count right
+    /// on early returns, panics, and tasks aborted at shutdown.
+    pub fn connection(self: &Arc<Self>) -> ConnectionGuard {
+        let active = self.active_connections.fetch_add(1, Ordering::Relaxed) + 1;
+        self.connections_total.fetch_add(1, Ordering::Relaxed);
+        self.peak_connections.fetch_max(active, Ordering::Relaxed);
+        ConnectionGuard {
+            stats: self.clone(),
+        }
     }
 
     /// Wrap a dialed stream so every byte is accounted globally and
@@ -78,11 +117,57 @@ impl StatsRegistry {
     pub fn wrap(&self, tag: &str, stream: BoxedStream) -> BoxedStream {
         Box::pin(CountingStream {
             inner: stream,
-            global: self.global.clone(),
-            tagged: self.tag_counters(tag),
+            sinks: vec![self.global.clone(), self.tag_counters(tag)],
+        })
+    }
+
+    /// [`StatsRegistry::wrap`] for an engine session: also counts one
+    /// tunnel and its bytes under `inbound`, and the bytes into the
+    /// connection's own counters.
+    pub(crate) fn wrap_session(
+        &self,
+        outbound: &str,
+        inbound: &'static str,
+        stream: BoxedStream,
+        conn: Arc<Counters>,
+    ) -> BoxedStream {
+        let inbound_bytes = {
+            let mut per_inbound = self.per_inbound.lock().expect("stats poisoned");
+            let entry = per_inbound.entry(inbound).or_default();
+            entry.sessions += 1;
+            entry.bytes.clone()
+        };
+        Box::pin(CountingStream {
+            inner: stream,
+            sinks: vec![
+                self.global.clone(),
+                self.tag_counters(outbound),
+                inbound_bytes,
+                conn,
+            ],
         })
     }
 
+    /// Lifetime totals per inbound protocol ("socks5", "http", ...); only
+    /// inbounds that have carried a session appear.
+    pub fn by_inbound(&self) -> BTreeMap<String, InboundStats> {
+        let per_inbound = self.per_inbound.lock().expect("stats poisoned");
+        per_inbound
+            .iter()
+            .map(|(tag, entry)| {
+                let (total_up, total_down) = entry.bytes.load();
+                (
+                    tag.to_string(),
+                    InboundStats {
+                        sessions: entry.sessions,
+                        total_up,
+                        total_down,
+                    },
+                )
+            })
+            .collect()
+    }
+
     /// Current totals plus rates against the previous call (used by the
     /// 1 Hz tick; the mutex only guards the previous-tick values).
     pub fn snapshot(&self) -> StatsSnapshot {
@@ -96,23 +181,49 @@ impl StatsRegistry {
             active_connections: self.active_connections.load(Ordering::Relaxed) as u32,
         }
     }
+
+    /// Lifetime totals for a run that has been up for `uptime`.
+    pub fn summary(&self, uptime: Duration) -> RunSummary {
+        RunSummary {
+            uptime_secs: uptime.as_secs(),
+            connections_total: self.connections_total.load(Ordering::Relaxed),
+            peak_connections: self.peak_connections.load(Ordering::Relaxed),
+            total_up: self.global.up.load(Ordering::Relaxed),
+            total_down: self.global.down.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// One active connection; see [`StatsRegistry::connection`].
+pub struct ConnectionGuard {
+    stats: Arc<StatsRegistry>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.stats
+            .active_connections
+            .fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 struct CountingStream {
     inner: BoxedStream,
-    global: Arc<Counters>,
-    tagged: Arc<Counters>,
+    /// Every counter set this stream's bytes are added to.
+    sinks: Vec<Arc<Counters>>,
 }
 
 impl CountingStream {
     fn count_up(&self, n: usize) {
-        self.global.up.fetch_add(n as u64, Ordering::Relaxed);
-        self.tagged.up.fetch_add(n as u64, Ordering::Relaxed);
+        for sink in &self.sinks {
+            sink.up.fetch_add(n as u64, Ordering::Relaxed);
+        }
     }
 
     fn count_down(&self, n: usize) {
-        self.global.down.fetch_add(n as u64, Ordering::Relaxed);
-        self.tagged.down.fetch_add(n as u64, Ordering::Relaxed);
+        for sink in &self.sinks {
+            sink.down.fetch_add(n as u64, Ordering::Relaxed);
+        }
     }
 }
 
@@ -159,3 +270,50 @@ impl AsyncWrite for CountingStream {
         Pin::new(&mut self.inner).poll_shutdown(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn summary_reflects_recorded_activity() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let stats = StatsRegistry::new();
+        let first = stats.connection();
+        let second = stats.connection();
+        drop(second);
+        let third = stats.connection();
+        drop(third);
+        drop(first);
+
+        let (near, mut far) = tokio::io::duplex(64);
+        let mut wrapped = stats.wrap("direct", Box::pin(near));
+        wrapped.write_all(b"hello").await.unwrap();
+        far.write_all(b"hi").await.unwrap();
+        let mut buf = [0u8; 2];
+        wrapped.read_exact(&mut buf).await.unwrap();
+
+        let summary = stats.summary(Duration::from_secs(90));
+        assert_eq!(summary.uptime_secs, 90);
+        assert_eq!(summary.connections_total, 3);
+        assert_eq!(summary.peak_connections, 2);
+        assert_eq!(summary.total_up, 5);
+        assert_eq!(summary.total_down, 2);
+        assert_eq!(stats.snapshot().active_connections, 0);
+    }
+
+    #[tokio::test]
+    async fn aborted_task_releases_its_connection() {
+        let stats = StatsRegistry::new();
+        let guard = stats.connection();
+        let task = tokio::spawn(async move {
+            let _guard = guard;
+            std::future::pending::<()>().await;
+        });
+        assert_eq!(stats.snapshot().active_connections, 1);
+        task.abort();
+        let _ = task.await;
+        assert_eq!(stats.snapshot().active_connections, 0);
+    }
+}
@@ -9,6 +9,7 @@
 pub mod tls;
 pub mod ws;
 
+use std::net::IpAddr;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
@@ -29,23 +30,29 @@ pub enum Transport {
 }
 
 impl Transport {
-    /// Connect to `server:port` and wrap per the transport.
-    pub async fn connect(&self, server: &str, port: u16) -> Result<BoxedStream, CoreError> {
+    /// Connect to `server:port` (from `bind`, if set) and wrap per the
+    /// transport.
+    pub async fn connect(
+        &self,
+        server: &str,
+        port: u16,
+        bind: Option<IpAddr>,
+    ) -> Result<BoxedStream, CoreError> {
         match self {
             Transport::Tcp => {
-                let tcp = ws::tcp_connect(server, port).await?;
+                let tcp = ws::tcp_connect(server, port, bind).await?;
                 Ok(Box::pin(tcp))
             }
             Transport::Tls(cfg) => {
-                let tcp = ws::tcp_connect(server, port).await?;
+                let tcp = ws::tcp_connect(server, port, bind).await?;
                 tls::wrap(tcp, server, cfg).await
             }
             Transport::Ws(cfg) => {
-                let tcp = ws::tcp_connect(server, port).await?;
+                let tcp = ws::tcp_connect(server, port, bind).await?;
                 ws::wrap(Box::pin(tcp), server, port, cfg).await
             }
             Transport::WsOverTls(ws_cfg, tls_cfg) => {
-                let tcp = ws::tcp_connect(server, port).await?;
+                let tcp = ws::tcp_connect(server, port, bind).await?;
                 let tls = tls::wrap(tcp, server, tls_cfg).await?;
                 ws::wrap(tls, server, port, ws_cfg).await
             }
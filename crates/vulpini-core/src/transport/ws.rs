@@ -6,6 +6,7 @@
 //! is the only TLS stack here.
 
 use std::io;
+use std::net::IpAddr;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
@@ -72,9 +73,16 @@ pub async fn wrap(
 }
 
 /// TCP connect helper shared by ws transports.
-pub async fn tcp_connect(server: &str, port: u16) -> Result<TcpStream, CoreError> {
-    let tcp =
-        tokio::time::timeout(super::CONNECT_TIMEOUT, TcpStream::connect((server, port))).await??;
+pub async fn tcp_connect(
+    server: &str,
+    port: u16,
+    bind: Option<IpAddr>,
+) -> Result<TcpStream, CoreError> {
+    let tcp = tokio::time::timeout(
+        super::CONNECT_TIMEOUT,
+        crate::common::dial::tcp_connect((server, port), bind),
+    )
+    .await??;
     tcp.set_nodelay(true).ok();
     Ok(tcp)
 }
@@ -0,0 +1,544 @@
+//! End-to-end tests for the engine knobs in `EngineConfig`: a real engine
+//! on an ephemeral port, driven by raw SOCKS5/HTTP clients.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing_subscriber::layer::SubscriberExt;
+
+use vulpini_core::common::{BoxedStream, CoreError, Session};
+use vulpini_core::inbound::InboundKind;
+use vulpini_core::outbound::{Outbound, OutboundRegistry};
+use vulpini_core::{EngineConfig, EngineHandle, Mode, Router};
+
+/// Sleeps for `delay`, then hands back one end of an in-memory pipe whose
+/// other end echoes. Lets tests control dial latency precisely.
+struct DelayedEcho {
+    delay: Duration,
+}
+
+#[async_trait]
+impl Outbound for DelayedEcho {
+    fn tag(&self) -> &str {
+        "delayed"
+    }
+
+    async fn dial_tcp(&self, _sess: &Session) -> Result<BoxedStream, CoreError> {
+        tokio::time::sleep(self.delay).await;
+        let (near, mut far) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            loop {
+                match far.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => {
+                        if far.write_all(&buf[..n]).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(near))
+    }
+}
+
+/// Engine whose every (non-private) session goes to a `DelayedEcho`.
+async fn start_engine(delay: Duration, config: EngineConfig) -> EngineHandle {
+    let mut registry = OutboundRegistry::new();
+    registry.register(Arc::new(DelayedEcho { delay }));
+    let router = Router::from_config(Mode::Rule, &["MATCH,delayed".to_string()]).unwrap();
+    EngineHandle::start(
+        "127.0.0.1:0".parse().unwrap(),
+        Arc::new(registry),
+        router,
+        config,
+    )
+    .await
+    .unwrap()
+}
+
+/// SOCKS5 CONNECT to a domain target; returns the stream and the REP code.
+async fn socks5_connect_domain(
+    proxy: std::net::SocketAddr,
+    host: &str,
+    port: u16,
+) -> (TcpStream, u8) {
+    let mut s = TcpStream::connect(proxy).await.unwrap();
+    s.write_all(&[0x05, 0x01, 0x00]).await.unwrap();
+    let mut sel = [0u8; 2];
+    s.read_exact(&mut sel).await.unwrap();
+    assert_eq!(sel, [0x05, 0x00]);
+
+    let mut req = vec![0x05, 0x01, 0x00, 0x03, host.len() as u8];
+    req.extend_from_slice(host.as_bytes());
+    req.extend_from_slice(&port.to_be_bytes());
+    s.write_all(&req).await.unwrap();
+
+    let mut rep = [0u8; 10];
+    s.read_exact(&mut rep).await.unwrap();
+    (s, rep[1])
+}
+
+/// HTTP CONNECT to a domain target; returns the stream and the status line.
+async fn http_connect_domain(
+    proxy: std::net::SocketAddr,
+    host: &str,
+    port: u16,
+) -> (TcpStream, String) {
+    let mut s = TcpStream::connect(proxy).await.unwrap();
+    let req = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n\r\n");
+    s.write_all(req.as_bytes()).await.unwrap();
+    let mut buf = vec![0u8; 256];
+    let n = s.read(&mut buf).await.unwrap();
+    let head = String::from_utf8_lossy(&buf[..n]);
+    let status = head.lines().next().unwrap_or_default().to_string();
+    (s, status)
+}
+
+/// Open one proxied session and echo a few bytes through it.
+async fn round_trip(proxy: std::net::SocketAddr) {
+    let (mut s, rep) = socks5_connect_domain(proxy, "echo.test", 80).await;
+    assert_eq!(rep, 0x00);
+    s.write_all(b"ping").await.unwrap();
+    let mut buf = [0u8; 4];
+    s.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+}
+
+/// Mirror every log record into the returned receiver while the guard lives.
+/// `#[tokio::test]` is single-threaded, so the engine's tasks see the
+/// thread-local default subscriber too.
+fn capture_logs() -> (
+    tracing::subscriber::DefaultGuard,
+    tokio::sync::broadcast::Receiver<vulpini_core::logbus::LogEvent>,
+) {
+    let (tx, rx) = vulpini_core::logbus::channel(256);
+    let subscriber =
+        tracing_subscriber::registry().with(vulpini_core::logbus::BroadcastLayer::new(tx));
+    (tracing::subscriber::set_default(subscriber), rx)
+}
+
+fn drain_messages(
+    rx: &mut tokio::sync::broadcast::Receiver<vulpini_core::logbus::LogEvent>,
+) -> Vec<String> {
+    let mut out = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        out.push(event.message);
+    }
+    out
+}
+
+#[tokio::test]
+async fn slow_setup_is_logged() {
+    let (_guard, mut logs) = capture_logs();
+    let engine = start_engine(
+        Duration::from_millis(150),
+        EngineConfig {
+            slow_request_threshold_ms: Some(50),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    round_trip(engine.local_addr()).await;
+
+    let messages = drain_messages(&mut logs);
+    let slow = messages
+        .iter()
+        .find(|m| m.contains("slow connection setup"))
+        .expect("slow setup must be logged");
+    assert!(slow.contains("echo.test:80"), "got: {slow}");
+    assert!(slow.contains("outbound=delayed"), "got: {slow}");
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn fast_setup_is_not_logged() {
+    let (_guard, mut logs) = capture_logs();
+    let engine = start_engine(
+        Duration::ZERO,
+        EngineConfig {
+            slow_request_threshold_ms: Some(2_000),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    round_trip(engine.local_addr()).await;
+
+    let messages = drain_messages(&mut logs);
+    assert!(
+        !messages.iter().any(|m| m.contains("slow connection setup")),
+        "fast setup logged as slow: {messages:?}"
+    );
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn credentials_are_enforced_from_the_first_connection() {
+    let engine = start_engine(
+        Duration::ZERO,
+        EngineConfig {
+            users: vec![vulpini_core::inbound::ProxyUser {
+                username: "alice".into(),
+                password: "wonder".into(),
+            }],
+            ..Default::default()
+        },
+    )
+    .await;
+    let proxy = engine.local_addr();
+
+    // The very first client, offering only no-auth, is refused.
+    let mut s = TcpStream::connect(proxy).await.unwrap();
+    s.write_all(&[0x05, 0x01, 0x00]).await.unwrap();
+    let mut sel = [0u8; 2];
+    s.read_exact(&mut sel).await.unwrap();
+    assert_eq!(sel, [0x05, 0xFF]);
+    drop(s);
+
+    let (s, status) = http_connect_domain(proxy, "echo.test", 80).await;
+    assert!(status.starts_with("HTTP/1.1 407"), "got: {status}");
+    drop(s);
+    assert_eq!(engine.stats_snapshot().total_up, 0);
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn paused_inbound_refuses_only_its_protocol() {
+    let engine = start_engine(Duration::ZERO, EngineConfig::default()).await;
+    let proxy = engine.local_addr();
+
+    assert!(engine.pause_inbound(InboundKind::Http));
+    assert!(!engine.pause_inbound(InboundKind::Http), "already paused");
+    assert!(engine.is_inbound_paused(InboundKind::Http));
+
+    let (s, status) = http_connect_domain(proxy, "echo.test", 80).await;
+    assert!(status.starts_with("HTTP/1.1 503"), "got: {status}");
+    drop(s);
+    // SOCKS5 shares the listener and keeps working.
+    round_trip(proxy).await;
+
+    assert!(engine.resume_inbound(InboundKind::Http));
+    assert!(!engine.resume_inbound(InboundKind::Http), "already running");
+    let (s, status) = http_connect_domain(proxy, "echo.test", 80).await;
+    assert!(status.starts_with("HTTP/1.1 200"), "got: {status}");
+    drop(s);
+
+    assert!(engine.pause_inbound(InboundKind::Socks5));
+    let (s, rep) = socks5_connect_domain(proxy, "echo.test", 80).await;
+    assert_ne!(rep, 0x00, "paused socks5 must refuse");
+    drop(s);
+
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn maintenance_refuses_new_sessions_but_keeps_live_ones() {
+    let engine = start_engine(Duration::ZERO, EngineConfig::default()).await;
+    let proxy = engine.local_addr();
+
+    // Opened before maintenance: must keep relaying throughout.
+    let (mut live, rep) = socks5_connect_domain(proxy, "echo.test", 80).await;
+    assert_eq!(rep, 0x00);
+
+    assert!(engine.set_maintenance(true));
+    assert!(!engine.set_maintenance(true), "already on");
+    assert!(engine.is_maintenance());
+
+    let (s, status) = http_connect_domain(proxy, "echo.test", 80).await;
+    assert!(status.starts_with("HTTP/1.1 503"), "got: {status}");
+    drop(s);
+    let (s, rep) = socks5_connect_domain(proxy, "echo.test", 80).await;
+    assert_ne!(rep, 0x00, "socks5 must refuse in maintenance");
+    drop(s);
+
+    live.write_all(b"still").await.unwrap();
+    let mut buf = [0u8; 5];
+    live.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"still");
+    drop(live);
+
+    assert!(engine.set_maintenance(false));
+    assert!(!engine.is_maintenance());
+    round_trip(proxy).await;
+
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn accept_log_is_sampled() {
+    let (_guard, mut logs) = capture_logs();
+    let engine = start_engine(
+        Duration::ZERO,
+        EngineConfig {
+            accept_log_sample_rate: Some(0.25),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    for _ in 0..8 {
+        round_trip(engine.local_addr()).await;
+    }
+
+    let accepted = drain_messages(&mut logs)
+        .into_iter()
+        .filter(|m| m.contains("connection accepted"))
+        .count();
+    assert_eq!(accepted, 2, "a quarter of 8 accepts");
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn socks4a_shares_the_mixed_port() {
+    let engine = start_engine(Duration::ZERO, EngineConfig::default()).await;
+
+    let mut s = TcpStream::connect(engine.local_addr()).await.unwrap();
+    s.write_all(b"\x04\x01\x00\x50\x00\x00\x00\x01legacy\x00echo.test\x00")
+        .await
+        .unwrap();
+    let mut rep = [0u8; 8];
+    s.read_exact(&mut rep).await.unwrap();
+    assert_eq!(rep[..2], [0x00, 0x5A], "request granted");
+
+    s.write_all(b"ping").await.unwrap();
+    let mut buf = [0u8; 4];
+    s.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+    drop(s);
+
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn live_connections_are_listed_until_closed() {
+    let engine = start_engine(Duration::ZERO, EngineConfig::default()).await;
+
+    let (mut s, rep) = socks5_connect_domain(engine.local_addr(), "echo.test", 80).await;
+    assert_eq!(rep, 0x00);
+    s.write_all(b"ping").await.unwrap();
+    let mut buf = [0u8; 4];
+    s.read_exact(&mut buf).await.unwrap();
+
+    let conns = engine.connections();
+    assert_eq!(conns.len(), 1);
+    let conn = &conns[0];
+    assert_eq!(conn.inbound, Some("socks5"));
+    assert_eq!(conn.target.as_deref(), Some("echo.test:80"));
+    assert_eq!(conn.outbound.as_deref(), Some("delayed"));
+    assert_eq!((conn.up, conn.down), (4, 4));
+    assert_eq!(conn.peer, s.local_addr().unwrap());
+
+    drop(s);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !engine.connections().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("closed connection must leave the table");
+
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn killed_connection_is_closed_and_unlisted() {
+    let engine = start_engine(Duration::ZERO, EngineConfig::default()).await;
+    let proxy = engine.local_addr();
+
+    let (mut victim, rep) = socks5_connect_domain(proxy, "echo.test", 80).await;
+    assert_eq!(rep, 0x00);
+    let (mut bystander, rep) = socks5_connect_domain(proxy, "echo.test", 80).await;
+    assert_eq!(rep, 0x00);
+
+    let victim_port = victim.local_addr().unwrap().port();
+    let id = engine
+        .connections()
+        .iter()
+        .find(|c| c.peer.port() == victim_port)
+        .expect("victim listed")
+        .id;
+    assert!(engine.kill_connection(id));
+
+    let mut buf = [0u8; 4];
+    let n = tokio::time::timeout(Duration::from_secs(5), victim.read(&mut buf))
+        .await
+        .expect("killed tunnel must close")
+        .unwrap_or(0);
+    assert_eq!(n, 0);
+    assert!(!engine.kill_connection(id), "unknown once gone");
+    assert!(engine.connections().iter().all(|c| c.id != id));
+
+    // The other tunnel is untouched.
+    bystander.write_all(b"ping").await.unwrap();
+    bystander.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+    drop(bystander);
+
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn stalled_handshakes_are_dropped() {
+    let engine = start_engine(
+        Duration::ZERO,
+        EngineConfig {
+            handshake_timeout_secs: Some(1),
+            ..Default::default()
+        },
+    )
+    .await;
+    let proxy = engine.local_addr();
+
+    // Silent after connect, and stuck halfway through a SOCKS5 greeting.
+    let silent = TcpStream::connect(proxy).await.unwrap();
+    let mut partial = TcpStream::connect(proxy).await.unwrap();
+    partial.write_all(&[0x05, 0x02]).await.unwrap();
+
+    for mut s in [silent, partial] {
+        let mut buf = [0u8; 16];
+        let n = tokio::time::timeout(Duration::from_secs(5), s.read(&mut buf))
+            .await
+            .expect("stalled handshake must be dropped")
+            .unwrap_or(0);
+        assert_eq!(n, 0);
+    }
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !engine.connections().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("dropped handshakes must leave the table");
+
+    // Prompt clients are unaffected.
+    round_trip(proxy).await;
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn slowloris_connections_do_not_starve_real_clients() {
+    let engine = start_engine(
+        Duration::ZERO,
+        EngineConfig {
+            handshake_timeout_secs: Some(1),
+            ..Default::default()
+        },
+    )
+    .await;
+    let proxy = engine.local_addr();
+
+    // A crowd of idle sockets, half of them dribbling an unfinished
+    // HTTP request header.
+    let mut idle = Vec::new();
+    for i in 0..64 {
+        let mut s = TcpStream::connect(proxy).await.unwrap();
+        if i % 2 == 0 {
+            s.write_all(b"CONNECT echo.test:80 HTTP/1.1\r\nHost: ")
+                .await
+                .unwrap();
+        }
+        idle.push(s);
+    }
+
+    // Both protocols still serve while the crowd holds on.
+    round_trip(proxy).await;
+    let (s, status) = http_connect_domain(proxy, "echo.test", 80).await;
+    assert!(status.starts_with("HTTP/1.1 200"), "got: {status}");
+    drop(s);
+
+    // And every idle socket is closed once its deadline passes.
+    for mut s in idle {
+        let mut buf = [0u8; 16];
+        let n = tokio::time::timeout(Duration::from_secs(5), s.read(&mut buf))
+            .await
+            .expect("idle connection outlived the handshake timeout")
+            .unwrap_or(0);
+        assert_eq!(n, 0);
+    }
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn shutdown_lets_tunnels_finish_within_the_grace() {
+    let engine = start_engine(
+        Duration::ZERO,
+        EngineConfig {
+            shutdown_grace_secs: Some(30),
+            ..Default::default()
+        },
+    )
+    .await;
+    let proxy = engine.local_addr();
+    let (mut live, rep) = socks5_connect_domain(proxy, "echo.test", 80).await;
+    assert_eq!(rep, 0x00);
+
+    let stopping = tokio::spawn(engine.shutdown());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    // The listener is gone, but the tunnel keeps relaying.
+    assert!(TcpStream::connect(proxy).await.is_err());
+    live.write_all(b"still here").await.unwrap();
+    let mut buf = [0u8; 10];
+    live.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"still here");
+    assert!(!stopping.is_finished());
+
+    // Closing the last tunnel ends the drain well before the grace runs out.
+    drop(live);
+    let summary = tokio::time::timeout(Duration::from_secs(5), stopping)
+        .await
+        .expect("drain must end once tunnels close")
+        .unwrap();
+    assert_eq!(summary.connections_total, 1);
+}
+
+#[tokio::test]
+async fn shutdown_aborts_tunnels_after_the_grace() {
+    let engine = start_engine(
+        Duration::ZERO,
+        EngineConfig {
+            shutdown_grace_secs: Some(1),
+            ..Default::default()
+        },
+    )
+    .await;
+    let (mut live, rep) = socks5_connect_domain(engine.local_addr(), "echo.test", 80).await;
+    assert_eq!(rep, 0x00);
+
+    tokio::time::timeout(Duration::from_secs(5), engine.shutdown())
+        .await
+        .expect("lingering tunnel must be aborted after the grace");
+    let mut buf = [0u8; 1];
+    assert_eq!(live.read(&mut buf).await.unwrap_or(0), 0);
+}
+
+#[tokio::test]
+async fn stats_are_broken_down_by_inbound() {
+    let engine = start_engine(Duration::ZERO, EngineConfig::default()).await;
+    let proxy = engine.local_addr();
+
+    round_trip(proxy).await;
+    round_trip(proxy).await;
+    let (mut s, status) = http_connect_domain(proxy, "echo.test", 80).await;
+    assert!(status.starts_with("HTTP/1.1 200"), "got: {status}");
+    s.write_all(b"hello").await.unwrap();
+    let mut buf = [0u8; 5];
+    s.read_exact(&mut buf).await.unwrap();
+    drop(s);
+
+    let by_inbound = engine.inbound_stats();
+    let socks5 = &by_inbound["socks5"];
+    assert_eq!(socks5.sessions, 2);
+    assert_eq!((socks5.total_up, socks5.total_down), (8, 8));
+    let http = &by_inbound["http"];
+    assert_eq!(http.sessions, 1);
+    assert_eq!((http.total_up, http.total_down), (5, 5));
+    assert!(!by_inbound.contains_key("socks4"));
+
+    engine.shutdown().await;
+}
@@ -2,6 +2,7 @@
 //! engine without restarting the listener.
 
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use async_trait::async_trait;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -10,7 +11,7 @@ use tokio::net::{TcpListener, TcpStream};
 use vulpini_core::common::{BoxedStream, CoreError, Session};
 use vulpini_core::outbound::{Outbound, OutboundRegistry};
 use vulpini_core::router::{Mode, RouteRule};
-use vulpini_core::{EngineHandle, Router};
+use vulpini_core::{EngineConfig, EngineHandle, Router};
 
 /// Test double: dials the fixed echo address regardless of the session
 /// target (a real proxy outbound would connect to its server instead).
@@ -97,6 +98,7 @@ async fn selector_and_router_hot_swap() {
         "127.0.0.1:0".parse().unwrap(),
         Arc::new(registry),
         Router::new(Mode::Global, vec![]),
+        EngineConfig::default(),
     )
     .await
     .unwrap();
@@ -142,6 +144,87 @@ async fn selector_and_router_hot_swap() {
     engine.shutdown().await;
 }
 
+/// Test double for one node: an in-memory echo that counts the dials made
+/// through it and every byte it receives.
+#[derive(Default)]
+struct CountingNode {
+    dials: AtomicUsize,
+    received: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Outbound for CountingNode {
+    fn tag(&self) -> &str {
+        "counting-node"
+    }
+
+    async fn dial_tcp(&self, _sess: &Session) -> Result<BoxedStream, CoreError> {
+        self.dials.fetch_add(1, Ordering::SeqCst);
+        let (near, mut far) = tokio::io::duplex(4096);
+        let received = self.received.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            loop {
+                match far.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => {
+                        received.fetch_add(n, Ordering::SeqCst);
+                        if far.write_all(&buf[..n]).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(near))
+    }
+}
+
+#[tokio::test]
+async fn tunnel_keeps_its_node_across_selector_swap() {
+    let registry = OutboundRegistry::new();
+    let selector = registry.selector();
+    let engine = EngineHandle::start(
+        "127.0.0.1:0".parse().unwrap(),
+        Arc::new(registry),
+        Router::new(Mode::Global, vec![]),
+        EngineConfig::default(),
+    )
+    .await
+    .unwrap();
+    let proxy = engine.local_addr();
+
+    let node_a = Arc::new(CountingNode::default());
+    let node_b = Arc::new(CountingNode::default());
+    selector.set(node_a.clone());
+    let (ok, mut tunnel) = socks5_domain_connect(proxy, "example.com", 443).await;
+    assert!(ok);
+    assert_echo_roundtrip(&mut tunnel).await;
+
+    // Switching nodes mid-tunnel must not move the established tunnel:
+    // its traffic keeps flowing through the node it was dialed with.
+    selector.set(node_b.clone());
+    let before = node_a.received.load(Ordering::SeqCst);
+    for _ in 0..3 {
+        assert_echo_roundtrip(&mut tunnel).await;
+    }
+    assert!(node_a.received.load(Ordering::SeqCst) > before);
+    assert_eq!(node_a.dials.load(Ordering::SeqCst), 1);
+    assert_eq!(node_b.dials.load(Ordering::SeqCst), 0);
+    assert_eq!(node_b.received.load(Ordering::SeqCst), 0);
+
+    // New sessions pick up the new node.
+    let (ok, mut fresh) = socks5_domain_connect(proxy, "example.com", 443).await;
+    assert!(ok);
+    assert_echo_roundtrip(&mut fresh).await;
+    assert_eq!(node_b.dials.load(Ordering::SeqCst), 1);
+    assert_eq!(node_a.dials.load(Ordering::SeqCst), 1);
+
+    drop(tunnel);
+    drop(fresh);
+    engine.shutdown().await;
+}
+
 #[test]
 fn rule_display_parse_roundtrip_stability() {
     // Rules stored as strings in config.json must round-trip losslessly.
@@ -49,6 +49,7 @@ async fn start_engine() -> (EngineHandle, std::net::SocketAddr) {
         "127.0.0.1:0".parse().unwrap(),
         registry,
         vulpini_core::Router::new(vulpini_core::Mode::Direct, vec![]),
+        vulpini_core::EngineConfig::default(),
     )
     .await
     .unwrap();
@@ -155,6 +156,58 @@ async fn half_close_propagates_and_terminates() {
         .expect("engine drain hung");
 }
 
+/// Start a server that reads its whole request up to EOF and only then
+/// answers with the byte count — the shape of `ssh`/`git` sessions that
+/// signal end-of-input by shutting down their write half.
+async fn start_reply_after_eof() -> std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                stream.read_to_end(&mut request).await.unwrap();
+                let reply = format!("got {} bytes", request.len());
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            });
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn client_half_close_keeps_the_reply_direction_open() {
+    let server = start_reply_after_eof().await;
+    let (engine, proxy) = start_engine().await;
+
+    async fn exchange(mut s: TcpStream) -> Vec<u8> {
+        s.write_all(b"twelve bytes").await.unwrap();
+        s.shutdown().await.unwrap(); // FIN towards the server, keep reading
+        let mut reply = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), s.read_to_end(&mut reply))
+            .await
+            .expect("reply never arrived after half-close")
+            .unwrap();
+        reply
+    }
+
+    let reply = exchange(socks5_connect(proxy, server).await).await;
+    assert_eq!(reply, b"got 12 bytes");
+
+    // Same through the throttled, counted stream stack.
+    engine.set_config(vulpini_core::EngineConfig {
+        max_bandwidth_kbps: Some(1_000),
+        ..Default::default()
+    });
+    let reply = exchange(http_connect(proxy, server).await).await;
+    assert_eq!(reply, b"got 12 bytes");
+
+    tokio::time::timeout(Duration::from_secs(5), engine.shutdown())
+        .await
+        .expect("engine drain hung");
+}
+
 #[tokio::test]
 async fn stats_events_tick_with_traffic() {
     let echo = start_echo(None).await;
@@ -196,6 +249,7 @@ async fn port_fallback_lands_on_a_free_port() {
         taken,
         registry,
         vulpini_core::Router::new(vulpini_core::Mode::Direct, vec![]),
+        vulpini_core::EngineConfig::default(),
     )
     .await
     .expect("fallback should find a free port");
@@ -209,6 +263,33 @@ async fn port_fallback_lands_on_a_free_port() {
     drop(blocker);
 }
 
+#[tokio::test]
+async fn outbound_dials_from_the_bind_address() {
+    // The target reports back the source address it saw.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, peer) = listener.accept().await.unwrap();
+        stream
+            .write_all(peer.ip().to_string().as_bytes())
+            .await
+            .unwrap();
+    });
+    let (engine, proxy) = start_engine().await;
+    engine.set_config(vulpini_core::EngineConfig {
+        outbound_bind_address: Some("127.0.0.2".parse().unwrap()),
+        ..Default::default()
+    });
+
+    let mut s = socks5_connect(proxy, target).await;
+    let mut seen = String::new();
+    s.read_to_string(&mut seen).await.unwrap();
+    assert_eq!(seen, "127.0.0.2");
+
+    drop(s);
+    engine.shutdown().await;
+}
+
 #[tokio::test]
 async fn unreachable_target_reports_error() {
     let (engine, proxy) = start_engine().await;
@@ -224,7 +305,7 @@ async fn unreachable_target_reports_error() {
     let mut rep = [0u8; 10];
     s.read_exact(&mut rep).await.unwrap();
     assert_eq!(rep[0], 0x05);
-    assert_ne!(rep[1], 0x00, "failure must be reported, got success");
+    assert_eq!(rep[1], 0x05, "refusal must be reported as such");
 
     drop(s);
     engine.shutdown().await;
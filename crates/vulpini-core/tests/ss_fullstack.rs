@@ -166,6 +166,7 @@ async fn socks5_to_shadowsocks_full_stack() {
         "127.0.0.1:0".parse().unwrap(),
         Arc::new(registry),
         vulpini_core::Router::new(vulpini_core::Mode::Global, vec![]),
+        vulpini_core::EngineConfig::default(),
     )
     .await
     .unwrap();
@@ -230,7 +231,7 @@ async fn delay_test_through_real_outbound() {
         password: "delay-pw".into(),
     });
     let probe = format!("http://probe.test:{}/generate_204", http_addr.port());
-    let delay = vulpini_core::delay::test_delay(&node, &probe, Duration::from_secs(5))
+    let delay = vulpini_core::delay::test_delay(&node, &probe, Duration::from_secs(5), None)
         .await
         .expect("delay probe failed");
     assert!(
@@ -1,12 +1,16 @@
 //! IPC commands: thin adapters over vulpini-core. All fallible commands
 //! return `Result<T, String>` so the frontend gets plain error strings.
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Manager, State};
-use vulpini_core::node::{Node, NodeId, NodeSource, parse_link};
-use vulpini_core::stats::StatsSnapshot;
+use vulpini_core::config::LinkImport;
+use vulpini_core::conntrack::ConnectionInfo;
+use vulpini_core::inbound::InboundKind;
+use vulpini_core::node::{NodeId, NodeSource};
+use vulpini_core::stats::{InboundStats, StatsSnapshot};
 use vulpini_core::{EngineHandle, Mode};
 
 use crate::AppState;
@@ -25,6 +29,10 @@ pub struct CoreStatusView {
     listen: String,
     mode: Mode,
     active_node: Option<String>,
+    /// Inbound protocols currently refusing new sessions.
+    paused_inbounds: Vec<String>,
+    /// Whole engine refusing new sessions (see `set_maintenance`).
+    maintenance: bool,
 }
 
 #[derive(Serialize)]
@@ -50,6 +58,8 @@ pub struct ImportFailure {
 #[derive(Serialize)]
 pub struct ImportResult {
     added: usize,
+    /// Links already present, in the store or earlier in the same batch.
+    skipped: usize,
     failed: Vec<ImportFailure>,
 }
 
@@ -132,9 +142,12 @@ pub async fn core_start(app: AppHandle, state: State<'_, AppState>) -> CmdResult
     }
     state.sync_selector().await;
     let router = state.build_router().await;
-    let listen = state.store.read().await.config().listen;
+    let (listen, engine_config) = {
+        let store = state.store.read().await;
+        (store.config().listen, store.config().engine.clone())
+    };
     let engine = Arc::new(
-        EngineHandle::start_with_fallback(listen, state.registry.clone(), router)
+        EngineHandle::start_with_fallback(listen, state.registry.clone(), router, engine_config)
             .await
             .map_err(err)?,
     );
@@ -191,7 +204,9 @@ pub async fn core_stop(app: AppHandle, state: State<'_, AppState>) -> CmdResult<
     match engine {
         Some(engine) => {
             match Arc::try_unwrap(engine) {
-                Ok(handle) => handle.shutdown().await,
+                Ok(handle) => {
+                    handle.shutdown().await;
+                }
                 Err(_) => return Err("engine still referenced".into()),
             }
             let _ = app.emit("core:status", false);
@@ -205,14 +220,69 @@ pub async fn core_stop(app: AppHandle, state: State<'_, AppState>) -> CmdResult<
 pub async fn core_status(state: State<'_, AppState>) -> CmdResult<CoreStatusView> {
     let store = state.store.read().await;
     let config = store.config();
+    let engine = state.engine.read().await;
+    let paused_inbounds: Vec<String> = engine
+        .as_ref()
+        .map(|e| {
+            InboundKind::ALL
+                .into_iter()
+                .filter(|k| e.is_inbound_paused(*k))
+                .map(|k| k.tag().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let maintenance = engine.as_ref().is_some_and(|e| e.is_maintenance());
     Ok(CoreStatusView {
-        running: state.engine.read().await.is_some(),
+        running: engine.is_some(),
         listen: config.listen.to_string(),
         mode: config.mode,
         active_node: config.active_node.map(|id| id.to_string()),
+        paused_inbounds,
+        maintenance,
     })
 }
 
+/// Pause or resume one inbound protocol ("socks5" / "socks4" / "http")
+/// on the running core. Errors when the inbound is already in the
+/// requested state.
+#[tauri::command]
+pub async fn set_inbound_paused(
+    state: State<'_, AppState>,
+    inbound: String,
+    paused: bool,
+) -> CmdResult<()> {
+    let kind = InboundKind::from_tag(&inbound).ok_or(format!("unknown inbound '{inbound}'"))?;
+    let engine = state.engine.read().await;
+    let engine = engine.as_ref().ok_or("core not running")?;
+    let changed = if paused {
+        engine.pause_inbound(kind)
+    } else {
+        engine.resume_inbound(kind)
+    };
+    if !changed {
+        return Err(format!(
+            "{inbound} inbound already {}",
+            if paused { "paused" } else { "running" }
+        ));
+    }
+    Ok(())
+}
+
+/// Switch maintenance mode on the running core: every new session is
+/// refused while on, live connections are untouched.
+#[tauri::command]
+pub async fn set_maintenance(state: State<'_, AppState>, on: bool) -> CmdResult<()> {
+    let engine = state.engine.read().await;
+    let engine = engine.as_ref().ok_or("core not running")?;
+    if !engine.set_maintenance(on) {
+        return Err(format!(
+            "maintenance already {}",
+            if on { "on" } else { "off" }
+        ));
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn set_mode(state: State<'_, AppState>, mode: String) -> CmdResult<()> {
     let mode = parse_mode(&mode)?;
@@ -267,24 +337,20 @@ pub async fn import_share_links(
 ) -> CmdResult<ImportResult> {
     let mut store = state.store.write().await;
     let mut added = 0usize;
+    let mut skipped = 0usize;
     let mut failed = Vec::new();
 
-    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
-        match parse_link(line) {
-            Ok((name, config)) => {
-                let node = Node::new(name, NodeSource::Manual, config);
-                if store
-                    .config()
-                    .nodes
-                    .iter()
-                    .any(|n| n.stable_key == node.stable_key)
-                {
-                    continue; // duplicate: skip silently
-                }
-                store.config_mut().nodes.push(node);
-                added += 1;
-            }
-            Err(e) => failed.push(ImportFailure {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let outcomes = store.config_mut().import_links(lines.iter().copied());
+    for (line, outcome) in lines.iter().zip(outcomes) {
+        match outcome {
+            LinkImport::Added(_) => added += 1,
+            LinkImport::Duplicate(_) => skipped += 1,
+            LinkImport::Invalid(e) => failed.push(ImportFailure {
                 line: line.chars().take(60).collect(),
                 error: e.to_string(),
             }),
@@ -293,7 +359,11 @@ pub async fn import_share_links(
     store.save().map_err(err)?;
     drop(store);
     let _ = app.emit("nodes:changed", ());
-    Ok(ImportResult { added, failed })
+    Ok(ImportResult {
+        added,
+        skipped,
+        failed,
+    })
 }
 
 #[tauri::command]
@@ -514,14 +584,15 @@ pub async fn test_node_delay(
             .cloned()
             .ok_or("node not found")?
     };
-    let (probe_url, timeout) = {
+    let (probe_url, timeout, bind) = {
         let store = state.store.read().await;
         (
             store.config().proxy.probe_url.clone(),
-            std::time::Duration::from_secs(store.config().proxy.delay_timeout_secs),
+            store.config().proxy.delay_timeout(),
+            store.config().engine.outbound_bind_address,
         )
     };
-    let result = vulpini_core::delay::test_delay(&node.config, &probe_url, timeout).await;
+    let result = vulpini_core::delay::test_delay(&node.config, &probe_url, timeout, bind).await;
 
     let (ms, error) = match &result {
         Ok(d) => (Some(d.as_millis() as u64), None),
@@ -567,17 +638,19 @@ pub async fn test_all_delays(app: AppHandle, state: State<'_, AppState>) -> CmdR
     use futures::StreamExt;
     let keys: std::collections::HashMap<_, _> =
         nodes.iter().map(|(id, _, k)| (*id, k.clone())).collect();
-    let (probe_url, timeout) = {
+    let (probe_url, timeout, bind) = {
         let store = state.store.read().await;
         (
             store.config().proxy.probe_url.clone(),
-            std::time::Duration::from_secs(store.config().proxy.delay_timeout_secs),
+            store.config().proxy.delay_timeout(),
+            store.config().engine.outbound_bind_address,
         )
     };
     let mut results = vulpini_core::delay::test_all(
         nodes.into_iter().map(|(id, c, _)| (id, c)).collect(),
         probe_url,
         timeout,
+        bind,
         8,
     );
 
@@ -705,6 +778,7 @@ pub async fn patch_config(
     let mut listen_changed = false;
     {
         let mut store = state.store.write().await;
+        let before = store.config().clone();
         let config = store.config_mut();
         if let Some(listen) = &patch.listen {
             let addr: std::net::SocketAddr = listen.parse().map_err(err)?;
@@ -747,6 +821,14 @@ pub async fn patch_config(
             config.proxy.sysproxy_override = bypass.clone();
         }
         store.save().map_err(err)?;
+        for change in before.diff(store.config()) {
+            tracing::info!(
+                path = %change.path,
+                old = ?change.old,
+                new = ?change.new,
+                "config changed"
+            );
+        }
     }
 
     if state.engine.read().await.is_some() {
@@ -770,6 +852,36 @@ pub async fn get_stats_snapshot(state: State<'_, AppState>) -> CmdResult<Option<
     Ok(engine.as_ref().map(|e| e.stats_snapshot()))
 }
 
+/// Sessions and bytes per inbound protocol (empty when stopped).
+#[tauri::command]
+pub async fn get_inbound_stats(
+    state: State<'_, AppState>,
+) -> CmdResult<BTreeMap<String, InboundStats>> {
+    let engine = state.engine.read().await;
+    Ok(engine
+        .as_ref()
+        .map(|e| e.inbound_stats())
+        .unwrap_or_default())
+}
+
+/// Live connections on the running core (empty when stopped).
+#[tauri::command]
+pub async fn list_connections(state: State<'_, AppState>) -> CmdResult<Vec<ConnectionInfo>> {
+    let engine = state.engine.read().await;
+    Ok(engine.as_ref().map(|e| e.connections()).unwrap_or_default())
+}
+
+/// Close one live connection (id from `list_connections`).
+#[tauri::command]
+pub async fn kill_connection(state: State<'_, AppState>, id: u64) -> CmdResult<()> {
+    let engine = state.engine.read().await;
+    let engine = engine.as_ref().ok_or("core not running")?;
+    if !engine.kill_connection(id) {
+        return Err(format!("no connection with id {id}"));
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn update_geo_data(state: State<'_, AppState>) -> CmdResult<(u64, u64)> {
     let geo = {
@@ -88,6 +88,8 @@ pub fn run() {
             commands::core_start,
             commands::core_stop,
             commands::core_status,
+            commands::set_inbound_paused,
+            commands::set_maintenance,
             commands::set_mode,
             commands::list_nodes,
             commands::import_share_links,
@@ -104,6 +106,9 @@ pub fn run() {
             commands::get_config,
             commands::patch_config,
             commands::get_stats_snapshot,
+            commands::get_inbound_stats,
+            commands::list_connections,
+            commands::kill_connection,
             commands::update_geo_data,
         ])
         .setup(move |app| {
@@ -117,7 +122,7 @@ pub fn run() {
             std::fs::create_dir_all(&data_dir).ok();
             let config_path: PathBuf = data_dir.join("config.json");
 
-            let mut store = ConfigStore::load(&config_path).expect("load config");
+            let mut store = ConfigStore::load(&config_path)?;
             // Geo data lives in the app data dir, not the CWD.
             if store.config().geo.data_dir.as_os_str() == "vulpini-data" {
                 store.config_mut().geo.data_dir = data_dir.join("data");
@@ -6,6 +6,7 @@ import Home from './pages/Home';
 import Nodes from './pages/Nodes';
 import Subscriptions from './pages/Subscriptions';
 import Rules from './pages/Rules';
+import Connections from './pages/Connections';
 import Logs from './pages/Logs';
 import Settings from './pages/Settings';
 
@@ -47,6 +48,7 @@ export default function App() {
         {page === 'nodes' && <Nodes />}
         {page === 'subs' && <Subscriptions />}
         {page === 'rules' && <Rules />}
+        {page === 'conns' && <Connections />}
         {page === 'logs' && <Logs />}
         {page === 'settings' && <Settings collapsed={collapsed} onToggleCollapsed={toggleCollapsed} />}
       </div>
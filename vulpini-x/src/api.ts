@@ -3,11 +3,15 @@ import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 export type Mode = 'global' | 'rule' | 'direct';
 
+export type Inbound = 'socks5' | 'socks4' | 'http';
+
 export interface CoreStatus {
   running: boolean;
   listen: string;
   mode: Mode;
   active_node: string | null;
+  paused_inbounds: string[];
+  maintenance: boolean;
 }
 
 export interface NodeView {
@@ -39,6 +43,23 @@ export interface StatsSnapshot {
   active_connections: number;
 }
 
+export interface InboundStats {
+  sessions: number;
+  total_up: number;
+  total_down: number;
+}
+
+export interface ConnectionInfo {
+  id: number;
+  peer: string;
+  inbound: string | null;
+  target: string | null;
+  outbound: string | null;
+  up: number;
+  down: number;
+  duration_ms: number;
+}
+
 export interface ConfigView {
   listen: string;
   mode: Mode;
@@ -65,6 +86,8 @@ export interface LogEvent {
 
 export interface ImportResult {
   added: number;
+  /** Duplicates of existing nodes or of earlier lines in the batch. */
+  skipped: number;
   failed: { line: string; error: string }[];
 }
 
@@ -86,6 +109,9 @@ export const api = {
   coreStart: () => invoke<void>('core_start'),
   coreStop: () => invoke<void>('core_stop'),
   coreStatus: () => invoke<CoreStatus>('core_status'),
+  setInboundPaused: (inbound: Inbound, paused: boolean) =>
+    invoke<void>('set_inbound_paused', { inbound, paused }),
+  setMaintenance: (on: boolean) => invoke<void>('set_maintenance', { on }),
   setMode: (mode: Mode) => invoke<void>('set_mode', { mode }),
   listNodes: () => invoke<NodeView[]>('list_nodes'),
   importShareLinks: (text: string) => invoke<ImportResult>('import_share_links', { text }),
@@ -103,6 +129,9 @@ export const api = {
   getConfig: () => invoke<ConfigView>('get_config'),
   patchConfig: (patch: Partial<ConfigView>) => invoke<ConfigView>('patch_config', { patch }),
   getStatsSnapshot: () => invoke<StatsSnapshot | null>('get_stats_snapshot'),
+  getInboundStats: () => invoke<Record<string, InboundStats>>('get_inbound_stats'),
+  listConnections: () => invoke<ConnectionInfo[]>('list_connections'),
+  killConnection: (id: number) => invoke<void>('kill_connection', { id }),
   updateGeoData: () => invoke<[number, number]>('update_geo_data'),
 };
 
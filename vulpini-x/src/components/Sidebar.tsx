@@ -1,14 +1,15 @@
-import { Globe, Home, ListOrdered, Rss, ScrollText, Settings } from 'lucide-react';
+import { Cable, Globe, Home, ListOrdered, Rss, ScrollText, Settings } from 'lucide-react';
 import clsx from 'clsx';
 import TrafficWidget from './TrafficWidget';
 
-export type PageId = 'home' | 'nodes' | 'subs' | 'rules' | 'logs' | 'settings';
+export type PageId = 'home' | 'nodes' | 'subs' | 'rules' | 'conns' | 'logs' | 'settings';
 
 const NAV: { id: PageId; label: string; icon: React.ReactNode }[] = [
   { id: 'home', label: '首页', icon: <Home size={17} /> },
   { id: 'nodes', label: '代理', icon: <Globe size={17} /> },
   { id: 'subs', label: '订阅', icon: <Rss size={17} /> },
   { id: 'rules', label: '规则', icon: <ListOrdered size={17} /> },
+  { id: 'conns', label: '连接', icon: <Cable size={17} /> },
   { id: 'logs', label: '日志', icon: <ScrollText size={17} /> },
   { id: 'settings', label: '设置', icon: <Settings size={17} /> },
 ];
@@ -0,0 +1,134 @@
+import { useEffect, useState } from 'react';
+import { X } from 'lucide-react';
+import BasePage from '../components/BasePage';
+import Switch from '../components/Switch';
+import { useApp } from '../store';
+import { api, type ConnectionInfo, type Inbound, type InboundStats } from '../api';
+
+const INBOUNDS: { id: Inbound; label: string }[] = [
+  { id: 'socks5', label: 'SOCKS5' },
+  { id: 'socks4', label: 'SOCKS4' },
+  { id: 'http', label: 'HTTP' },
+];
+
+const POLL_MS = 1000;
+
+function formatTotal(bytes: number): string {
+  if (bytes >= 1 << 30) return `${(bytes / (1 << 30)).toFixed(2)} GB`;
+  if (bytes >= 1 << 20) return `${(bytes / (1 << 20)).toFixed(1)} MB`;
+  if (bytes >= 1 << 10) return `${(bytes / (1 << 10)).toFixed(1)} KB`;
+  return `${bytes} B`;
+}
+
+function formatDuration(ms: number): string {
+  const secs = Math.floor(ms / 1000);
+  if (secs >= 3600) return `${Math.floor(secs / 3600)}h${Math.floor((secs % 3600) / 60)}m`;
+  if (secs >= 60) return `${Math.floor(secs / 60)}m${secs % 60}s`;
+  return `${secs}s`;
+}
+
+/** Live view of the running core: per-inbound traffic and pause switches,
+ * maintenance mode, and every open connection with a kill button. */
+export default function Connections() {
+  const status = useApp((s) => s.status);
+  const setInboundPaused = useApp((s) => s.setInboundPaused);
+  const setMaintenance = useApp((s) => s.setMaintenance);
+  const killConnection = useApp((s) => s.killConnection);
+  const [inboundStats, setInboundStats] = useState<Record<string, InboundStats>>({});
+  const [conns, setConns] = useState<ConnectionInfo[]>([]);
+
+  const running = status?.running ?? false;
+
+  // Both commands answer empty when the core is stopped, so one poll
+  // loop covers every state.
+  useEffect(() => {
+    let cancelled = false;
+    const poll = async () => {
+      const [stats, list] = await Promise.all([
+        api.getInboundStats().catch((): Record<string, InboundStats> => ({})),
+        api.listConnections().catch((): ConnectionInfo[] => []),
+      ]);
+      if (cancelled) return;
+      setInboundStats(stats);
+      setConns([...list].sort((a, b) => b.duration_ms - a.duration_ms));
+    };
+    void poll();
+    const t = setInterval(() => void poll(), POLL_MS);
+    return () => {
+      cancelled = true;
+      clearInterval(t);
+    };
+  }, []);
+
+  const kill = async (id: number) => {
+    await killConnection(id);
+    setConns((list) => list.filter((c) => c.id !== id));
+  };
+
+  return (
+    <BasePage
+      title="连接"
+      actions={
+        <div className="row" style={{ gap: 8 }}>
+          <span className="muted small">维护模式</span>
+          <Switch
+            checked={status?.maintenance ?? false}
+            disabled={!running}
+            onChange={(on) => void setMaintenance(on)}
+            label="maintenance"
+          />
+        </div>
+      }
+    >
+      <div className="home-stats">
+        {INBOUNDS.map((inbound) => {
+          const stats = inboundStats[inbound.id];
+          const paused = status?.paused_inbounds.includes(inbound.id) ?? false;
+          return (
+            <div key={inbound.id} className="home-stat">
+              <div className="conn-inbound__head">
+                <span className="small">{inbound.label}</span>
+                <Switch
+                  checked={running && !paused}
+                  disabled={!running}
+                  onChange={(on) => void setInboundPaused(inbound.id, !on)}
+                  label={`${inbound.id} inbound`}
+                />
+              </div>
+              <div className="home-stat__value">{stats?.sessions ?? 0} 次</div>
+              <div className="muted small">
+                ↑ {formatTotal(stats?.total_up ?? 0)} · ↓ {formatTotal(stats?.total_down ?? 0)}
+              </div>
+            </div>
+          );
+        })}
+      </div>
+
+      <div className="conn-list">
+        {conns.length === 0 && (
+          <div className="empty">{running ? '暂无活动连接' : '核心未运行'}</div>
+        )}
+        {conns.map((c) => (
+          <div key={c.id} className="conn-row">
+            <span className="badge">{c.inbound ?? '-'}</span>
+            <div className="conn-row__main">
+              <div className="conn-row__target">{c.target ?? '握手中'}</div>
+              <div className="muted small">
+                {c.peer} → {c.outbound ?? '-'}
+              </div>
+            </div>
+            <div className="conn-row__numbers muted small">
+              <div>
+                ↑ {formatTotal(c.up)} · ↓ {formatTotal(c.down)}
+              </div>
+              <div>{formatDuration(c.duration_ms)}</div>
+            </div>
+            <button className="btn btn--sm" onClick={() => void kill(c.id)} title="断开">
+              <X size={14} />
+            </button>
+          </div>
+        ))}
+      </div>
+    </BasePage>
+  );
+}
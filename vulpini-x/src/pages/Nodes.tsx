@@ -104,16 +104,18 @@ export default function Nodes() {
 
     let allErrors = preErrors;
     let added = 0;
+    let skipped = 0;
     if (valid.length > 0) {
       const result = await importLinks(valid.join('\n'));
       added = result.added;
+      skipped = result.skipped;
       allErrors = [...preErrors, ...result.failed];
     }
     setImportErrors(allErrors);
     setMessage(
       valid.length === 0
         ? '没有可导入的有效链接'
-        : `已导入 ${added} 条${allErrors.length > 0 ? `，${allErrors.length} 条失败` : ''}`,
+        : `已导入 ${added} 条${skipped > 0 ? `，${skipped} 条重复已跳过` : ''}${allErrors.length > 0 ? `，${allErrors.length} 条失败` : ''}`,
     );
     if (added > 0) setText('');
   };
@@ -6,6 +6,7 @@ import {
   type CoreStatus,
   type DelayResultPayload,
   type ImportResult,
+  type Inbound,
   type LogEvent,
   type Mode,
   type NodeView,
@@ -36,6 +37,9 @@ interface AppState {
   startCore: () => Promise<void>;
   stopCore: () => Promise<void>;
   setMode: (mode: Mode) => Promise<void>;
+  setInboundPaused: (inbound: Inbound, paused: boolean) => Promise<void>;
+  setMaintenance: (on: boolean) => Promise<void>;
+  killConnection: (id: number) => Promise<void>;
   importLinks: (text: string) => Promise<ImportResult>;
   deleteNode: (id: string) => Promise<void>;
   selectNode: (id: string) => Promise<void>;
@@ -127,6 +131,20 @@ export const useApp = create<AppState>((set, get) => {
         await get().refreshStatus();
         await get().refreshConfig();
       }),
+    setInboundPaused: (inbound, paused) =>
+      safely(paused ? '暂停入站' : '恢复入站', async () => {
+        await api.setInboundPaused(inbound, paused);
+        await get().refreshStatus();
+      }),
+    setMaintenance: (on) =>
+      safely('切换维护模式', async () => {
+        await api.setMaintenance(on);
+        await get().refreshStatus();
+      }),
+    killConnection: (id) =>
+      safely('断开连接', async () => {
+        await api.killConnection(id);
+      }),
     importLinks: async (text) => {
       const result = await api.importShareLinks(text);
       await get().refreshNodes();